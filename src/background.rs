@@ -0,0 +1,470 @@
+use crate::error::SceneError;
+use crate::sampling::{Distribution2D, Sampler};
+use crate::scenedata::{Color, DirectionalLight, Light};
+use crate::vector3::Vector3;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+
+//ce vad razele care nu lovesc niciun obiect
+#[derive(Serialize, Deserialize)]
+pub enum Background {
+    Color(Color),
+    //interpolat dupa componenta y a directiei: -1 = bottom, 1 = top
+    Gradient { top: Color, bottom: Color },
+    Environment(EnvironmentMap),
+    Sky(Sky),
+}
+
+//cer analitic preetham; scena primeste automat o DirectionalLight pe directia soarelui
+#[derive(Serialize, Deserialize)]
+pub struct Sky {
+    //directia spre soare
+    pub sun_direction: Vector3,
+    //cat de incarcata e atmosfera: 2 = foarte senin, 10 = ceata
+    #[serde(default = "default_turbidity")]
+    pub turbidity: f64,
+    //culoarea solului, vazut sub orizont
+    #[serde(default = "default_ground_albedo")]
+    pub ground_albedo: Color,
+    #[serde(default = "default_sky_intensity")]
+    pub intensity: f32,
+    //intensitatea luminii soarelui; 0 = fara lumina directionala
+    #[serde(default = "default_sky_intensity")]
+    pub sun_intensity: f32,
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+fn default_ground_albedo() -> Color {
+    Color {
+        red: 0.1,
+        green: 0.1,
+        blue: 0.1,
+    }
+}
+
+fn default_sky_intensity() -> f32 {
+    1.0
+}
+
+//luminanta preetham e in kcd/m^2, o aducem in jurul lui 0..1
+const SKY_SCALE: f64 = 0.05;
+//raza unghiulara a discului solar, in radiani
+const SUN_ANGULAR_RADIUS: f64 = 0.0047;
+
+impl Default for Background {
+    fn default() -> Self {
+        Background::Color(Color::black())
+    }
+}
+
+//imagine equirectangulara (hdr sau ldr); centrul imaginii e directia -z
+#[derive(Serialize, Deserialize)]
+pub struct EnvironmentMap {
+    pub path: PathBuf,
+    #[serde(default = "default_environment_intensity")]
+    pub intensity: f32,
+    //rotatie in jurul axei y, in grade
+    #[serde(default)]
+    pub rotation: f64,
+    //daca e true, harta lumineaza si obiectele difuze, nu doar fundalul
+    #[serde(default)]
+    pub lighting: bool,
+    #[serde(default = "default_environment_samples")]
+    pub samples: u32,
+    #[serde(skip)]
+    data: Option<EnvironmentData>,
+}
+
+struct EnvironmentData {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+    //proportionala cu luminozitatea * sin(theta), ca directiile luminoase sa fie alese mai des
+    distribution: Distribution2D,
+}
+
+fn default_environment_intensity() -> f32 {
+    1.0
+}
+
+fn default_environment_samples() -> u32 {
+    16
+}
+
+impl Background {
+    pub fn color(&self, direction: &Vector3) -> Color {
+        match *self {
+            Background::Color(ref color) => color.clone(),
+            Background::Gradient {
+                ref top,
+                ref bottom,
+            } => {
+                let t = ((direction.normalize().y + 1.0) / 2.0) as f32;
+                bottom.clone() * (1.0 - t) + top.clone() * t
+            }
+            Background::Environment(ref environment) => environment.radiance(direction),
+            Background::Sky(ref sky) => sky.radiance(direction),
+        }
+    }
+
+    //lumina directionala care insoteste fundalul, daca exista
+    pub fn sun_light(&self) -> Option<Light> {
+        match *self {
+            Background::Sky(ref sky) => sky.sun_light(),
+            _ => None,
+        }
+    }
+
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), SceneError> {
+        match *self {
+            Background::Environment(ref mut environment) if !environment.is_loaded() => {
+                environment.load(base_dir)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl EnvironmentMap {
+    pub fn new(path: PathBuf) -> Result<EnvironmentMap, SceneError> {
+        let mut environment = EnvironmentMap {
+            path,
+            intensity: default_environment_intensity(),
+            rotation: 0.0,
+            lighting: false,
+            samples: default_environment_samples(),
+            data: None,
+        };
+        environment.load(Path::new(""))?;
+        Ok(environment)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.data.is_some()
+    }
+
+    //path e cautat in base_dir, ca la texturi
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), SceneError> {
+        let path = base_dir.join(&self.path);
+        let image = image::open(&path)
+            .map_err(|source| SceneError::MissingTexture { path, source })?
+            .to_rgb32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels: Vec<[f32; 3]> = image.pixels().map(|p| p.0).collect();
+
+        let weights: Vec<f64> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let theta = PI * ((i / width) as f64 + 0.5) / height as f64;
+                let luminance = 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2];
+                luminance as f64 * theta.sin()
+            })
+            .collect();
+        self.data = Some(EnvironmentData {
+            width,
+            height,
+            pixels,
+            distribution: Distribution2D::new(&weights, width, height),
+        });
+        Ok(())
+    }
+
+    //directie -> coordonate (u, v) in imagine, ambele in [0, 1)
+    fn direction_to_uv(&self, direction: &Vector3) -> (f64, f64) {
+        let direction = direction.normalize();
+        let phi = direction.x.atan2(-direction.z) + self.rotation.to_radians();
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0);
+        let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+        (u, v.min(1.0 - 1e-12))
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vector3 {
+        let phi = (u - 0.5) * 2.0 * PI - self.rotation.to_radians();
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        Vector3 {
+            x: sin_theta * phi.sin(),
+            y: cos_theta,
+            z: -sin_theta * phi.cos(),
+        }
+    }
+
+    pub fn radiance(&self, direction: &Vector3) -> Color {
+        let data = match self.data {
+            Some(ref data) => data,
+            None => return Color::black(),
+        };
+        let (u, v) = self.direction_to_uv(direction);
+        let x = ((u * data.width as f64) as usize).min(data.width - 1);
+        let y = ((v * data.height as f64) as usize).min(data.height - 1);
+        let [red, green, blue] = data.pixels[y * data.width + x];
+        Color { red, green, blue } * self.intensity
+    }
+
+    //directie aleasa proportional cu luminozitatea hartii; intoarce si densitatea
+    //in raport cu unghiul solid
+    pub fn sample(&self, sampler: &mut Sampler) -> Option<(Vector3, Color, f64)> {
+        let data = self.data.as_ref()?;
+        let ((u, v), pdf_uv) = data
+            .distribution
+            .sample(sampler.next_f64(), sampler.next_f64());
+        let sin_theta = (v * PI).sin();
+        if pdf_uv <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+        let direction = self.uv_to_direction(u, v);
+        Some((
+            direction,
+            self.radiance(&direction),
+            pdf_uv / (2.0 * PI * PI * sin_theta),
+        ))
+    }
+
+    pub fn pdf(&self, direction: &Vector3) -> f64 {
+        let data = match self.data {
+            Some(ref data) => data,
+            None => return 0.0,
+        };
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        data.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+//coeficientii functiei perez (a, b, c, d, e) pentru o turbiditate data
+fn perez_coefficients(turbidity: f64, table: [[f64; 2]; 5]) -> [f64; 5] {
+    table.map(|[slope, offset]| slope * turbidity + offset)
+}
+
+fn perez(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / cos_theta.max(0.01)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+}
+
+fn zenith_chromaticity(turbidity: f64, theta: f64, table: [[f64; 4]; 3]) -> f64 {
+    let row = |c: [f64; 4]| c[0] * theta.powi(3) + c[1] * theta * theta + c[2] * theta + c[3];
+    turbidity * turbidity * row(table[0]) + turbidity * row(table[1]) + row(table[2])
+}
+
+const PEREZ_Y: [[f64; 2]; 5] = [
+    [0.1787, -1.4630],
+    [-0.3554, 0.4275],
+    [-0.0227, 5.3251],
+    [0.1206, -2.5771],
+    [-0.0670, 0.3703],
+];
+const PEREZ_X: [[f64; 2]; 5] = [
+    [-0.0193, -0.2592],
+    [-0.0665, 0.0008],
+    [-0.0004, 0.2125],
+    [-0.0641, -0.8989],
+    [-0.0033, 0.0452],
+];
+const PEREZ_Y_CHROMA: [[f64; 2]; 5] = [
+    [-0.0167, -0.2608],
+    [-0.0950, 0.0092],
+    [-0.0079, 0.2102],
+    [-0.0441, -1.6537],
+    [-0.0109, 0.0529],
+];
+const ZENITH_X: [[f64; 4]; 3] = [
+    [0.00166, -0.00375, 0.00209, 0.0],
+    [-0.02903, 0.06377, -0.03202, 0.00394],
+    [0.11693, -0.21196, 0.06052, 0.25886],
+];
+const ZENITH_Y: [[f64; 4]; 3] = [
+    [0.00275, -0.00610, 0.00317, 0.0],
+    [-0.04214, 0.08970, -0.04153, 0.00516],
+    [0.15346, -0.26756, 0.06670, 0.26688],
+];
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Color {
+        red: (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0) as f32,
+        green: (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0) as f32,
+        blue: (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0) as f32,
+    }
+}
+
+impl Sky {
+    //unghiul zenital al soarelui, tinut deasupra orizontului ca formulele sa ramana valide
+    fn sun_zenith(&self) -> f64 {
+        let up = self.sun_direction.normalize().y.clamp(-1.0, 1.0);
+        up.acos().min(std::f64::consts::FRAC_PI_2 - 1e-3)
+    }
+
+    fn sky_radiance(&self, direction: &Vector3) -> Color {
+        let turbidity = self.turbidity;
+        let theta_sun = self.sun_zenith();
+        let sun = self.sun_direction.normalize();
+        let direction = direction.normalize();
+        let cos_theta = direction.y.max(0.0);
+        let gamma = direction.dot(&sun).clamp(-1.0, 1.0).acos();
+
+        let chi = (4.0 / 9.0 - turbidity / 120.0) * (std::f64::consts::PI - 2.0 * theta_sun);
+        let zenith_luminance =
+            (4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192;
+        let zenith_x = zenith_chromaticity(turbidity, theta_sun, ZENITH_X);
+        let zenith_y = zenith_chromaticity(turbidity, theta_sun, ZENITH_Y);
+
+        //valoarea la zenit scalata cu raportul perez dintre directia ceruta si zenit
+        let relative = |table| {
+            let coefficients = perez_coefficients(turbidity, table);
+            perez(&coefficients, cos_theta, gamma) / perez(&coefficients, 1.0, theta_sun)
+        };
+        xyy_to_rgb(
+            zenith_x * relative(PEREZ_X),
+            zenith_y * relative(PEREZ_Y_CHROMA),
+            zenith_luminance * relative(PEREZ_Y) * SKY_SCALE,
+        )
+    }
+
+    pub fn radiance(&self, direction: &Vector3) -> Color {
+        let direction = direction.normalize();
+        //sub orizont: solul, luminat de cerul de la zenit
+        if direction.y < 0.0 {
+            let up = Vector3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            };
+            return self.ground_albedo.clone() * self.sky_radiance(&up) * self.intensity;
+        }
+        let mut color = self.sky_radiance(&direction) * self.intensity;
+        let sun = self.sun_direction.normalize();
+        if self.sun_intensity > 0.0 && direction.dot(&sun) > SUN_ANGULAR_RADIUS.cos() {
+            if let Some(Light::Directional(ref light)) = self.sun_light() {
+                color = color + light.color.clone() * light.intensity;
+            }
+        }
+        color
+    }
+
+    //culoarea soarelui dupa ce trece prin atmosfera (rayleigh + aerosoli, ca la preetham)
+    pub fn sun_color(&self) -> Color {
+        let theta = self.sun_zenith();
+        let air_mass = 1.0 / (theta.cos() + 0.15 * (93.885 - theta.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |wavelength_um: f64| {
+            let rayleigh = (-0.008735 * wavelength_um.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * wavelength_um.powf(-1.3) * air_mass).exp();
+            (rayleigh * aerosol) as f32
+        };
+        Color {
+            red: transmittance(0.65),
+            green: transmittance(0.57),
+            blue: transmittance(0.475),
+        }
+    }
+
+    //None daca soarele e sub orizont sau lumina lui e dezactivata
+    pub fn sun_light(&self) -> Option<Light> {
+        if self.sun_intensity <= 0.0 || self.sun_direction.y <= 0.0 {
+            return None;
+        }
+        Some(Light::Directional(DirectionalLight {
+            direction: -self.sun_direction.normalize(),
+            color: self.sun_color(),
+            intensity: self.sun_intensity,
+        }))
+    }
+}
+
+#[test]
+fn test_environment_importance_sampling() {
+    let dir = std::env::temp_dir().join(format!("raytracer_env_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sky.png");
+    //o singura zona luminoasa, in dreapta sus
+    let image = image::RgbImage::from_fn(32, 16, |x, y| {
+        if (20..24).contains(&x) && (4..6).contains(&y) {
+            image::Rgb([255, 255, 255])
+        } else {
+            image::Rgb([1, 1, 1])
+        }
+    });
+    image.save(&path).unwrap();
+
+    let environment = EnvironmentMap::new(path).unwrap();
+    let mut sampler = Sampler::new(3);
+    let mut bright = 0;
+    for _ in 0..200 {
+        let (direction, radiance, pdf) = environment.sample(&mut sampler).unwrap();
+        assert!((environment.pdf(&direction) - pdf).abs() < 1e-6 * pdf.max(1.0));
+        if radiance.red > 0.5 {
+            bright += 1;
+        }
+    }
+    //zona luminoasa e sub 2% din imagine, dar trebuie sa primeasca majoritatea sample-urilor
+    assert!(bright > 150);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_gradient_background() {
+    let background = Background::Gradient {
+        top: Color {
+            red: 0.0,
+            green: 0.0,
+            blue: 1.0,
+        },
+        bottom: Color::black(),
+    };
+    let up = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    assert_eq!(background.color(&up).blue, 1.0);
+    assert_eq!(background.color(&-up).blue, 0.0);
+}
+
+#[test]
+fn test_sky_follows_the_sun() {
+    let sky = Sky {
+        sun_direction: Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: -1.0,
+        },
+        turbidity: 3.0,
+        ground_albedo: default_ground_albedo(),
+        intensity: 1.0,
+        sun_intensity: 2.0,
+    };
+    let luminance = |c: Color| 0.2126 * c.red + 0.7152 * c.green + 0.0722 * c.blue;
+    let near_sun = sky.radiance(&Vector3 {
+        x: 0.0,
+        y: 0.8,
+        z: -1.0,
+    });
+    let away = sky.radiance(&Vector3 {
+        x: 0.0,
+        y: 0.8,
+        z: 1.0,
+    });
+    assert!(luminance(near_sun) > luminance(away.clone()));
+    //cerul senin e albastru
+    assert!(away.blue > away.red);
+
+    match sky.sun_light() {
+        Some(Light::Directional(ref light)) => {
+            assert!(light.direction.y < 0.0 && light.direction.z > 0.0);
+            assert_eq!(light.intensity, 2.0);
+            //lumina soarelui e mai calda decat albul
+            assert!(light.color.red > light.color.blue);
+        }
+        _ => panic!("expected a sun light"),
+    }
+}
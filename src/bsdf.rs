@@ -0,0 +1,471 @@
+use crate::render::{Ray, SurfacePoint};
+use crate::sampling::Sampler;
+use crate::scenedata::{orthonormal_basis, Color, Element, SurfaceType};
+use crate::vector3::Vector3;
+use std::f64::consts::PI;
+
+//cum imprastie o suprafata lumina. toate directiile sunt in spatiul lumii si pleaca din
+//punctul lovit: outgoing spre privitor (-ray.direction), incoming spre lumina.
+//eval nu include cosinusul; integratorii il inmultesc singuri
+pub enum Bsdf {
+    Lambert {
+        color: Color,
+    },
+    //microfatete GGX (Trowbridge-Reitz); color e reflectanta la incidenta normala
+    Conductor {
+        color: Color,
+        alpha: f64,
+    },
+    //reflexie si refractie GGX; color coloreaza doar lumina transmisa
+    Dielectric {
+        color: Color,
+        index: f64,
+        alpha: f64,
+    },
+    //metallic-roughness: difuz + specular dielectric, amestecat spre metal dupa metallic
+    Principled {
+        color: Color,
+        metallic: f64,
+        alpha: f64,
+        specular: f64,
+    },
+}
+
+pub struct BsdfSample {
+    pub direction: Vector3,
+    //f * |cos| / pdf, factorul cu care se inmulteste throughput-ul
+    pub weight: Color,
+    pub pdf: f64,
+}
+
+//sub aceasta valoare GGX devine practic o functie delta si pierde precizie
+const MIN_ALPHA: f64 = 1e-3;
+
+fn alpha(roughness: f32) -> f64 {
+    let roughness = roughness.clamp(0.0, 1.0) as f64;
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+impl Bsdf {
+    //Reflective si Refractive au partea speculara perfecta tratata de integratori,
+    //aici ramane doar partea lor difuza
+    pub fn new(surface: &SurfaceType, color: Color) -> Bsdf {
+        match *surface {
+            SurfaceType::Diffuse
+            | SurfaceType::Reflective { .. }
+            | SurfaceType::Refractive { .. } => Bsdf::Lambert { color },
+            SurfaceType::Conductor { roughness } => Bsdf::Conductor {
+                color,
+                alpha: alpha(roughness),
+            },
+            SurfaceType::Dielectric { index, roughness } => Bsdf::Dielectric {
+                color,
+                index: index as f64,
+                alpha: alpha(roughness),
+            },
+            SurfaceType::Principled {
+                metallic,
+                roughness,
+                specular,
+            } => Bsdf::Principled {
+                color,
+                metallic: metallic.clamp(0.0, 1.0) as f64,
+                alpha: alpha(roughness),
+                specular: specular.max(0.0) as f64,
+            },
+        }
+    }
+
+    //culoarea (textura) si albedo-ul obiectului in punctul dat
+    pub fn at(object: &Element, ray: &Ray, hit: &SurfacePoint) -> Bsdf {
+        let color = object.surface_color(ray, hit) * object.albedo();
+        Bsdf::new(object.surface(), color)
+    }
+
+    pub fn eval(&self, outgoing: &Vector3, incoming: &Vector3, normal: &Vector3) -> Color {
+        let frame = Frame::facing(normal, outgoing);
+        let (wo, wi) = (frame.to_local(outgoing), frame.to_local(incoming));
+        if wo.z <= 0.0 {
+            return Color::black();
+        }
+        match *self {
+            Bsdf::Dielectric {
+                ref color,
+                index,
+                alpha,
+            } => dielectric_eval(color, frame.eta(index), alpha, &wo, &wi),
+            _ if wi.z <= 0.0 => Color::black(),
+            Bsdf::Lambert { ref color } => color.clone() * (1.0 / PI) as f32,
+            Bsdf::Conductor { ref color, alpha } => ggx_reflection(color, alpha, &wo, &wi),
+            Bsdf::Principled {
+                ref color,
+                metallic,
+                alpha,
+                specular,
+            } => {
+                let half = (wo + wi).normalize();
+                let f0 = principled_f0(color, metallic, specular);
+                //lumina care nu e reflectata specular de stratul dielectric ajunge difuza
+                let dielectric_f = schlick(&Color::gray((0.08 * specular) as f32), wo.dot(&half));
+                let diffuse = (1.0 - metallic) * (1.0 - dielectric_f.red as f64) / PI;
+                ggx_reflection(&f0, alpha, &wo, &wi) + color.clone() * diffuse as f32
+            }
+        }
+    }
+
+    //densitatea, in unghi solid, cu care sample alege directia incoming
+    pub fn pdf(&self, outgoing: &Vector3, incoming: &Vector3, normal: &Vector3) -> f64 {
+        let frame = Frame::facing(normal, outgoing);
+        let (wo, wi) = (frame.to_local(outgoing), frame.to_local(incoming));
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        match *self {
+            Bsdf::Dielectric { index, alpha, .. } => {
+                dielectric_pdf(frame.eta(index), alpha, &wo, &wi)
+            }
+            _ if wi.z <= 0.0 => 0.0,
+            Bsdf::Lambert { .. } => wi.z / PI,
+            Bsdf::Conductor { alpha, .. } => ggx_reflection_pdf(alpha, &wo, &wi),
+            Bsdf::Principled {
+                metallic, alpha, ..
+            } => {
+                let p = specular_probability(metallic);
+                p * ggx_reflection_pdf(alpha, &wo, &wi) + (1.0 - p) * wi.z / PI
+            }
+        }
+    }
+
+    pub fn sample(
+        &self,
+        outgoing: &Vector3,
+        normal: &Vector3,
+        sampler: &mut Sampler,
+    ) -> Option<BsdfSample> {
+        let frame = Frame::facing(normal, outgoing);
+        let wo = frame.to_local(outgoing);
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let wi = match *self {
+            Bsdf::Lambert { .. } => cosine_sample(sampler),
+            Bsdf::Conductor { alpha, .. } => reflect(&wo, &sample_ggx(alpha, sampler)),
+            Bsdf::Dielectric { index, alpha, .. } => {
+                let eta = frame.eta(index);
+                let micro_normal = sample_ggx(alpha, sampler);
+                let cos = wo.dot(&micro_normal);
+                if cos <= 0.0 {
+                    return None;
+                }
+                //reflexie sau refractie, cu probabilitatea data de fresnel
+                if sampler.next_f64() < fresnel_dielectric(cos, eta) {
+                    reflect(&wo, &micro_normal)
+                } else {
+                    refract(&wo, &micro_normal, eta)?
+                }
+            }
+            Bsdf::Principled {
+                metallic, alpha, ..
+            } => {
+                if sampler.next_f64() < specular_probability(metallic) {
+                    reflect(&wo, &sample_ggx(alpha, sampler))
+                } else {
+                    cosine_sample(sampler)
+                }
+            }
+        };
+
+        let direction = frame.to_world(&wi);
+        let pdf = self.pdf(outgoing, &direction, normal);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            weight: self.eval(outgoing, &direction, normal) * (wi.z.abs() / pdf) as f32,
+            direction,
+            pdf,
+        })
+    }
+
+    //cat din lumina ambientala uniforma e reflectata; sticla o lasa sa treaca
+    pub fn reflectance(&self) -> Color {
+        match *self {
+            Bsdf::Lambert { ref color }
+            | Bsdf::Conductor { ref color, .. }
+            | Bsdf::Principled { ref color, .. } => color.clone(),
+            Bsdf::Dielectric { .. } => Color::black(),
+        }
+    }
+}
+
+//cadrul local are z spre partea privitorului; flipped = privitorul e in spatele normalei
+struct Frame {
+    tangent: Vector3,
+    bitangent: Vector3,
+    normal: Vector3,
+    flipped: bool,
+}
+
+impl Frame {
+    fn facing(normal: &Vector3, outgoing: &Vector3) -> Frame {
+        let flipped = normal.dot(outgoing) < 0.0;
+        let normal = if flipped { -*normal } else { *normal };
+        let (tangent, bitangent) = orthonormal_basis(&normal);
+        Frame {
+            tangent,
+            bitangent,
+            normal,
+            flipped,
+        }
+    }
+
+    fn to_local(&self, v: &Vector3) -> Vector3 {
+        Vector3 {
+            x: v.dot(&self.tangent),
+            y: v.dot(&self.bitangent),
+            z: v.dot(&self.normal),
+        }
+    }
+
+    fn to_world(&self, v: &Vector3) -> Vector3 {
+        (self.tangent * v.x + self.bitangent * v.y + self.normal * v.z).normalize()
+    }
+
+    //indicele relativ pentru lumina care trece de partea privitorului in cealalta
+    fn eta(&self, index: f64) -> f64 {
+        if self.flipped {
+            1.0 / index
+        } else {
+            index
+        }
+    }
+}
+
+fn cosine_sample(sampler: &mut Sampler) -> Vector3 {
+    let (a, b) = (sampler.next_f64(), sampler.next_f64());
+    let r = a.sqrt();
+    let (sin, cos) = (2.0 * PI * b).sin_cos();
+    Vector3 {
+        x: r * cos,
+        y: r * sin,
+        z: (1.0 - a).max(0.0).sqrt(),
+    }
+}
+
+fn reflect(wo: &Vector3, micro_normal: &Vector3) -> Vector3 {
+    *micro_normal * (2.0 * wo.dot(micro_normal)) - *wo
+}
+
+//None la total internal reflection
+fn refract(wo: &Vector3, micro_normal: &Vector3, eta: f64) -> Option<Vector3> {
+    let cos_i = wo.dot(micro_normal);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(-*wo * (1.0 / eta) + *micro_normal * (cos_i / eta - cos_t))
+}
+
+//reflectanta fresnel pentru lumina care vine din mediul exterior, cos_i >= 0
+fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+fn schlick(f0: &Color, cos: f64) -> Color {
+    let t = (1.0 - cos.clamp(0.0, 1.0)).powi(5) as f32;
+    f0.clone() * (1.0 - t) + Color::gray(t)
+}
+
+fn ggx_d(alpha: f64, micro_normal: &Vector3) -> f64 {
+    if micro_normal.z <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let d = micro_normal.z * micro_normal.z * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+//umbrirea si mascarea smith, separabile
+fn smith_g1(alpha: f64, v: &Vector3) -> f64 {
+    let cos = v.z.abs();
+    let a2 = alpha * alpha;
+    2.0 * cos / (cos + (a2 + (1.0 - a2) * cos * cos).sqrt())
+}
+
+fn smith_g(alpha: f64, wo: &Vector3, wi: &Vector3) -> f64 {
+    smith_g1(alpha, wo) * smith_g1(alpha, wi)
+}
+
+//micro-normala cu densitatea D(m) * cos(theta_m)
+fn sample_ggx(alpha: f64, sampler: &mut Sampler) -> Vector3 {
+    let (a, b) = (sampler.next_f64(), sampler.next_f64());
+    let cos2 = (1.0 - a) / (1.0 + (alpha * alpha - 1.0) * a);
+    let sin = (1.0 - cos2).max(0.0).sqrt();
+    let (sin_phi, cos_phi) = (2.0 * PI * b).sin_cos();
+    Vector3 {
+        x: sin * cos_phi,
+        y: sin * sin_phi,
+        z: cos2.sqrt(),
+    }
+}
+
+fn ggx_reflection(f0: &Color, alpha: f64, wo: &Vector3, wi: &Vector3) -> Color {
+    let half = (*wo + *wi).normalize();
+    let value = ggx_d(alpha, &half) * smith_g(alpha, wo, wi) / (4.0 * wo.z * wi.z);
+    schlick(f0, wo.dot(&half)) * value as f32
+}
+
+fn ggx_reflection_pdf(alpha: f64, wo: &Vector3, wi: &Vector3) -> f64 {
+    let half = (*wo + *wi).normalize();
+    let cos = wo.dot(&half);
+    if cos <= 0.0 {
+        return 0.0;
+    }
+    ggx_d(alpha, &half) * half.z / (4.0 * cos)
+}
+
+//micro-normala care leaga wo de wi (wo.z > 0) si raportul de indici pe drum;
+//None daca directiile nu pot fi legate printr-o singura microfateta
+fn dielectric_half(eta: f64, wo: &Vector3, wi: &Vector3) -> Option<(Vector3, f64)> {
+    if wi.z == 0.0 {
+        return None;
+    }
+    let etap = if wi.z > 0.0 { 1.0 } else { eta };
+    let half = *wi * etap + *wo;
+    if half.dot(&half) == 0.0 {
+        return None;
+    }
+    let half = half.normalize();
+    let half = if half.z < 0.0 { -half } else { half };
+    if half.dot(wi) * wi.z < 0.0 || half.dot(wo) < 0.0 {
+        return None;
+    }
+    Some((half, etap))
+}
+
+fn dielectric_eval(color: &Color, eta: f64, alpha: f64, wo: &Vector3, wi: &Vector3) -> Color {
+    let (half, etap) = match dielectric_half(eta, wo, wi) {
+        Some(half) => half,
+        None => return Color::black(),
+    };
+    let fresnel = fresnel_dielectric(wo.dot(&half), eta);
+    let dg = ggx_d(alpha, &half) * smith_g(alpha, wo, wi);
+    if wi.z > 0.0 {
+        return Color::gray((dg * fresnel / (4.0 * wo.z * wi.z)) as f32);
+    }
+    //radianta se comprima cu 1/eta^2 cand intra intr-un mediu mai dens
+    let denom = (wi.dot(&half) + wo.dot(&half) / etap).powi(2) * wi.z * wo.z;
+    let value =
+        dg * (1.0 - fresnel) * (wi.dot(&half) * wo.dot(&half) / denom).abs() / (etap * etap);
+    color.clone() * value as f32
+}
+
+fn dielectric_pdf(eta: f64, alpha: f64, wo: &Vector3, wi: &Vector3) -> f64 {
+    let (half, etap) = match dielectric_half(eta, wo, wi) {
+        Some(half) => half,
+        None => return 0.0,
+    };
+    let fresnel = fresnel_dielectric(wo.dot(&half), eta);
+    let half_pdf = ggx_d(alpha, &half) * half.z;
+    if wi.z > 0.0 {
+        half_pdf / (4.0 * wo.dot(&half)) * fresnel
+    } else {
+        let denom = (wi.dot(&half) + wo.dot(&half) / etap).powi(2);
+        half_pdf * wi.dot(&half).abs() / denom * (1.0 - fresnel)
+    }
+}
+
+//reflectanta la incidenta normala: 0.08 * specular (4% la 0.5) pentru dielectric, culoarea pentru metal
+fn principled_f0(color: &Color, metallic: f64, specular: f64) -> Color {
+    Color::gray((0.08 * specular * (1.0 - metallic)) as f32) + color.clone() * metallic as f32
+}
+
+fn specular_probability(metallic: f64) -> f64 {
+    0.5 + 0.5 * metallic
+}
+
+#[test]
+fn test_bsdf_sampling_matches_eval_and_pdf() {
+    let normal = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let outgoing = Vector3 {
+        x: 0.4,
+        y: 0.8,
+        z: -0.2,
+    }
+    .normalize();
+    let white = Color::gray(1.0);
+    let mut sampler = Sampler::new(3);
+
+    //lambert alb reflecta exact toata lumina, indiferent de directie
+    let lambert = Bsdf::new(&SurfaceType::Diffuse, white.clone());
+    for _ in 0..16 {
+        let sample = lambert.sample(&outgoing, &normal, &mut sampler).unwrap();
+        assert!((sample.weight.red - 1.0).abs() < 1e-5);
+    }
+
+    //metallic = 1 e un conductor cu aceeasi culoare
+    let conductor = Bsdf::new(&SurfaceType::Conductor { roughness: 0.4 }, white.clone());
+    let principled = Bsdf::new(
+        &SurfaceType::Principled {
+            metallic: 1.0,
+            roughness: 0.4,
+            specular: 0.5,
+        },
+        white.clone(),
+    );
+    let mut energy = 0.0;
+    let samples = 4000;
+    for _ in 0..samples {
+        let sample = match conductor.sample(&outgoing, &normal, &mut sampler) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let incoming = sample.direction;
+        assert!((conductor.pdf(&outgoing, &incoming, &normal) - sample.pdf).abs() < 1e-9);
+        let there = conductor.eval(&outgoing, &incoming, &normal);
+        let back = conductor.eval(&incoming, &outgoing, &normal);
+        assert!((there.red - back.red).abs() <= 1e-4 * there.red.max(1.0));
+        let metal = principled.eval(&outgoing, &incoming, &normal);
+        assert!((there.green - metal.green).abs() <= 1e-4 * there.green.max(1.0));
+        energy += sample.weight.red as f64;
+    }
+    //GGX cu smith pierde putina energie, dar nu creeaza
+    let energy = energy / samples as f64;
+    assert!(energy > 0.85 && energy < 1.01, "energy {}", energy);
+
+    //sticla aproape lustruita la incidenta normala: ~4% reflectat, restul trece drept
+    let glass = Bsdf::new(
+        &SurfaceType::Dielectric {
+            index: 1.5,
+            roughness: 0.01,
+        },
+        white,
+    );
+    let mut reflected = 0;
+    for _ in 0..samples {
+        let sample = glass.sample(&normal, &normal, &mut sampler).unwrap();
+        if sample.direction.dot(&normal) > 0.0 {
+            reflected += 1;
+        } else {
+            assert!(sample.direction.dot(&normal) < -0.99);
+        }
+        assert!(
+            (glass.pdf(&normal, &sample.direction, &normal) - sample.pdf).abs() < 1e-6 * sample.pdf
+        );
+    }
+    let fraction = reflected as f64 / samples as f64;
+    assert!((fraction - 0.04).abs() < 0.015, "reflected {}", fraction);
+}
@@ -0,0 +1,484 @@
+use crate::render::{Hit, Ray};
+use crate::vector3::Vector3;
+
+//cate galeti folosim pe fiecare axa cand cautam split-ul cu SAH
+const SAH_BINS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+//costul relativ al unui pas de traversare fata de un test de intersectie
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3, max: Vector3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vector3 {
+                x: f64::INFINITY,
+                y: f64::INFINITY,
+                z: f64::INFINITY,
+            },
+            max: Vector3 {
+                x: f64::NEG_INFINITY,
+                y: f64::NEG_INFINITY,
+                z: f64::NEG_INFINITY,
+            },
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vector3 {
+                x: self.min.x.min(other.min.x),
+                y: self.min.y.min(other.min.y),
+                z: self.min.z.min(other.min.z),
+            },
+            max: Vector3 {
+                x: self.max.x.max(other.max.x),
+                y: self.max.y.max(other.max.y),
+                z: self.max.z.max(other.max.z),
+            },
+        }
+    }
+
+    pub fn grow(&self, point: &Vector3) -> Aabb {
+        self.union(&Aabb::new(*point, *point))
+    }
+
+    pub fn centroid(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    pub fn contains(&self, point: &Vector3, epsilon: f64) -> bool {
+        point.x >= self.min.x - epsilon
+            && point.x <= self.max.x + epsilon
+            && point.y >= self.min.y - epsilon
+            && point.y <= self.max.y + epsilon
+            && point.z >= self.min.z - epsilon
+            && point.z <= self.max.z + epsilon
+    }
+
+    //slab test, la fel ca la cub; inv_dir e precalculat o data pe raza
+    pub fn intersect(&self, ray: &Ray, inv_dir: &Vector3, t_max: f64) -> Option<f64> {
+        let t_x1 = (self.min.x - ray.origin.x) * inv_dir.x;
+        let t_x2 = (self.max.x - ray.origin.x) * inv_dir.x;
+        let t_y1 = (self.min.y - ray.origin.y) * inv_dir.y;
+        let t_y2 = (self.max.y - ray.origin.y) * inv_dir.y;
+        let t_z1 = (self.min.z - ray.origin.z) * inv_dir.z;
+        let t_z2 = (self.max.z - ray.origin.z) * inv_dir.z;
+
+        let t_enter = t_x1
+            .min(t_x2)
+            .max(t_y1.min(t_y2))
+            .max(t_z1.min(t_z2))
+            .max(0.0);
+        let t_exit = t_x1
+            .max(t_x2)
+            .min(t_y1.max(t_y2))
+            .min(t_z1.max(t_z2))
+            .min(t_max);
+
+        if t_enter > t_exit {
+            None
+        } else {
+            Some(t_enter)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum BvhNode {
+    Leaf {
+        bounds: Aabb,
+        first: usize,
+        count: usize,
+    },
+    Interior {
+        bounds: Aabb,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &Aabb {
+        match *self {
+            BvhNode::Leaf { ref bounds, .. } => bounds,
+            BvhNode::Interior { ref bounds, .. } => bounds,
+        }
+    }
+}
+
+//ierarhie de bounding box-uri peste o lista de primitive identificate prin index
+//primitivele fara bounding box (plane infinite) sunt tinute separat si testate mereu
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>,
+    primitive_count: usize,
+}
+
+struct BuildPrimitive {
+    index: usize,
+    bounds: Aabb,
+    centroid: Vector3,
+}
+
+impl Bvh {
+    pub fn build(bounds: &[Option<Aabb>]) -> Bvh {
+        let mut primitives = Vec::with_capacity(bounds.len());
+        let mut unbounded = Vec::new();
+        for (index, b) in bounds.iter().enumerate() {
+            match b {
+                Some(b) => primitives.push(BuildPrimitive {
+                    index,
+                    bounds: *b,
+                    centroid: b.centroid(),
+                }),
+                None => unbounded.push(index),
+            }
+        }
+
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            indices: Vec::with_capacity(primitives.len()),
+            unbounded,
+            primitive_count: bounds.len(),
+        };
+        if !primitives.is_empty() {
+            bvh.build_node(&mut primitives);
+        }
+        bvh
+    }
+
+    pub fn primitive_count(&self) -> usize {
+        self.primitive_count
+    }
+
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|n| *n.bounds())
+    }
+
+    fn build_node(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+        let bounds = primitives
+            .iter()
+            .fold(Aabb::empty(), |acc, p| acc.union(&p.bounds));
+        let node_index = self.nodes.len();
+
+        if primitives.len() <= 1 {
+            self.push_leaf(bounds, primitives);
+            return node_index;
+        }
+
+        let centroid_bounds = primitives
+            .iter()
+            .fold(Aabb::empty(), |acc, p| acc.grow(&p.centroid));
+
+        match Bvh::find_sah_split(primitives, &bounds, &centroid_bounds) {
+            Some((axis, split_bin)) => {
+                //nodul e rezervat inainte de copii ca radacina sa ramana pe pozitia 0
+                self.nodes.push(BvhNode::Leaf {
+                    bounds,
+                    first: 0,
+                    count: 0,
+                });
+
+                let mid = partition(primitives, |p| {
+                    sah_bin(&p.centroid, axis, &centroid_bounds) < split_bin
+                });
+                let (left_primitives, right_primitives) = primitives.split_at_mut(mid);
+                let left = self.build_node(left_primitives);
+                let right = self.build_node(right_primitives);
+                self.nodes[node_index] = BvhNode::Interior {
+                    bounds,
+                    left,
+                    right,
+                };
+            }
+            None => self.push_leaf(bounds, primitives),
+        }
+        node_index
+    }
+
+    fn push_leaf(&mut self, bounds: Aabb, primitives: &[BuildPrimitive]) {
+        let first = self.indices.len();
+        self.indices.extend(primitives.iter().map(|p| p.index));
+        self.nodes.push(BvhNode::Leaf {
+            bounds,
+            first,
+            count: primitives.len(),
+        });
+    }
+
+    //intoarce axa si galeata la care facem split-ul cu cel mai mic cost SAH,
+    //sau None daca e mai ieftin sa facem o frunza
+    fn find_sah_split(
+        primitives: &[BuildPrimitive],
+        bounds: &Aabb,
+        centroid_bounds: &Aabb,
+    ) -> Option<(usize, usize)> {
+        let leaf_cost = primitives.len() as f64;
+        let parent_area = bounds.surface_area();
+        let mut best: Option<(usize, usize, f64)> = None;
+
+        for axis in 0..3 {
+            let extent = centroid_bounds.max[axis] - centroid_bounds.min[axis];
+            if extent <= 0.0 {
+                continue;
+            }
+
+            let mut bin_bounds = [Aabb::empty(); SAH_BINS];
+            let mut bin_counts = [0usize; SAH_BINS];
+            for p in primitives {
+                let bin = sah_bin(&p.centroid, axis, centroid_bounds);
+                bin_counts[bin] += 1;
+                bin_bounds[bin] = bin_bounds[bin].union(&p.bounds);
+            }
+
+            for split_bin in 1..SAH_BINS {
+                let (mut left_bounds, mut left_count) = (Aabb::empty(), 0);
+                for i in 0..split_bin {
+                    left_bounds = left_bounds.union(&bin_bounds[i]);
+                    left_count += bin_counts[i];
+                }
+                let (mut right_bounds, mut right_count) = (Aabb::empty(), 0);
+                for i in split_bin..SAH_BINS {
+                    right_bounds = right_bounds.union(&bin_bounds[i]);
+                    right_count += bin_counts[i];
+                }
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+
+                let cost = TRAVERSAL_COST
+                    + (left_bounds.surface_area() * left_count as f64
+                        + right_bounds.surface_area() * right_count as f64)
+                        / parent_area.max(f64::MIN_POSITIVE);
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, split_bin, cost));
+                }
+            }
+        }
+
+        match best {
+            Some((axis, split_bin, cost))
+                if cost < leaf_cost || primitives.len() > MAX_LEAF_SIZE =>
+            {
+                Some((axis, split_bin))
+            }
+            _ => None,
+        }
+    }
+
+    //cea mai apropiata intersectie; hit(index) intersecteaza raza cu primitiva
+    pub fn closest_hit<F>(&self, ray: &Ray, mut hit: F) -> Option<(usize, Hit)>
+    where
+        F: FnMut(usize) -> Option<Hit>,
+    {
+        let mut closest: Option<(usize, Hit)> = None;
+        let mut test = |index: usize, closest: &mut Option<(usize, Hit)>| {
+            if let Some(h) = hit(index) {
+                if closest.is_none_or(|(_, best)| h.distance < best.distance) {
+                    *closest = Some((index, h));
+                }
+            }
+        };
+
+        for &index in &self.unbounded {
+            test(index, &mut closest);
+        }
+
+        if self.nodes.is_empty() {
+            return closest;
+        }
+
+        let inv_dir = Vector3 {
+            x: 1.0 / ray.direction.x,
+            y: 1.0 / ray.direction.y,
+            z: 1.0 / ray.direction.z,
+        };
+        let mut stack = vec![0usize];
+        while let Some(node_index) = stack.pop() {
+            let t_max = closest.map_or(f64::INFINITY, |(_, h)| h.distance);
+            let node = &self.nodes[node_index];
+            if node.bounds().intersect(ray, &inv_dir, t_max).is_none() {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { first, count, .. } => {
+                    for &index in &self.indices[first..first + count] {
+                        test(index, &mut closest);
+                    }
+                }
+                BvhNode::Interior { left, right, .. } => {
+                    //copilul mai apropiat e vizitat primul ca sa taiem mai mult din celalalt
+                    let t_left = self.nodes[left].bounds().intersect(ray, &inv_dir, t_max);
+                    let t_right = self.nodes[right].bounds().intersect(ray, &inv_dir, t_max);
+                    match (t_left, t_right) {
+                        (Some(l), Some(r)) if l <= r => {
+                            stack.push(right);
+                            stack.push(left);
+                        }
+                        (Some(_), Some(_)) => {
+                            stack.push(left);
+                            stack.push(right);
+                        }
+                        (Some(_), None) => stack.push(left),
+                        (None, Some(_)) => stack.push(right),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
+
+fn sah_bin(centroid: &Vector3, axis: usize, centroid_bounds: &Aabb) -> usize {
+    let axis_min = centroid_bounds.min[axis];
+    let extent = centroid_bounds.max[axis] - axis_min;
+    let offset = (centroid[axis] - axis_min) / extent;
+    ((offset * SAH_BINS as f64) as usize).min(SAH_BINS - 1)
+}
+
+fn partition<T, F>(items: &mut [T], pred: F) -> usize
+where
+    F: Fn(&T) -> bool,
+{
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if pred(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+#[test]
+fn test_bvh_matches_linear_trace() {
+    use crate::camera::Camera;
+    use crate::render::Intersectable;
+    use crate::scenedata::{
+        Color, Coloration, Element, Material, Plane, Scene, Sphere, SurfaceType,
+    };
+    use crate::vector3::Normal3;
+
+    let material = || Material {
+        coloration: Coloration::Color(Color::black()),
+        albedo: 1.0,
+        surface: SurfaceType::Diffuse,
+        specular: None,
+        emission: None,
+    };
+    //lcg simplu ca testul sa fie determinist
+    let mut state: u64 = 12345;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 33) as f64) / ((1u64 << 31) as f64)
+    };
+
+    let mut objects: Vec<Element> = (0..200)
+        .map(|_| {
+            Element::Sphere(Sphere {
+                center: Vector3 {
+                    x: next() * 20.0 - 10.0,
+                    y: next() * 20.0 - 10.0,
+                    z: -5.0 - next() * 30.0,
+                },
+                radius: 0.2 + next(),
+                material: material(),
+            })
+        })
+        .collect();
+    objects.push(Element::Plane(Plane {
+        p: Vector3 {
+            x: 0.0,
+            y: -8.0,
+            z: 0.0,
+        },
+        normal: Normal3 {
+            x: 0.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        material: material(),
+    }));
+
+    let mut scene = Scene::new(
+        1,
+        1,
+        Camera::new(Vector3::zero(), 90.0),
+        Vec::new(),
+        objects,
+    );
+
+    let rays: Vec<Ray> = (0..500)
+        .map(|_| Ray {
+            origin: Vector3::zero(),
+            direction: Vector3 {
+                x: next() * 2.0 - 1.0,
+                y: next() * 2.0 - 1.0,
+                z: -1.0,
+            }
+            .normalize(),
+            differential: None,
+        })
+        .collect();
+    let linear: Vec<Option<f64>> = rays
+        .iter()
+        .map(|r| {
+            //fara bvh: cel mai apropiat obiect dintre toate
+            scene
+                .objects()
+                .iter()
+                .filter_map(|o| o.intersect(r))
+                .min_by(|a, b| a.total_cmp(b))
+        })
+        .collect();
+
+    for (ray, expected) in rays.iter().zip(linear) {
+        assert_eq!(scene.trace(ray).unwrap().map(|i| i.distance), expected);
+    }
+
+    //inlocuirea obiectelor reface bvh-ul, deci noua sfera e gasita
+    let moved = Element::Sphere(Sphere {
+        center: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -100.0,
+        },
+        radius: 1.0,
+        material: material(),
+    });
+    scene.set_objects(vec![moved]);
+    let hit = scene.trace(&Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    });
+    assert_eq!(hit.unwrap().map(|i| i.distance), Some(99.0));
+}
@@ -0,0 +1,171 @@
+use crate::render::{Ray, RayDifferential};
+use crate::vector3::Vector3;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    pub eye: Vector3,
+    pub target: Vector3,
+    #[serde(default = "default_up")]
+    pub up: Vector3,
+    //fov vertical, in grade
+    pub fov: f64,
+    //daca e setat, latimea imaginii nu mai e derivata din fov * aspect ratio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub horizontal_fov: Option<f64>,
+    //implicit width / height
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_ratio: Option<f64>,
+    //rotatie in jurul directiei de privire, in grade
+    #[serde(default)]
+    pub roll: f64,
+}
+
+fn default_up() -> Vector3 {
+    Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    }
+}
+
+//baza ortonormala a camerei: right si up sunt pe planul senzorului, forward spre target
+pub struct CameraBasis {
+    pub right: Vector3,
+    pub up: Vector3,
+    pub forward: Vector3,
+}
+
+impl Camera {
+    //camera veche: din eye se priveste spre -z
+    pub fn new(eye: Vector3, fov: f64) -> Camera {
+        Camera {
+            eye,
+            target: eye
+                + Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: -1.0,
+                },
+            up: default_up(),
+            fov,
+            horizontal_fov: None,
+            aspect_ratio: None,
+            roll: 0.0,
+        }
+    }
+
+    pub fn look_at(eye: Vector3, target: Vector3, up: Vector3, fov: f64) -> Camera {
+        Camera {
+            eye,
+            target,
+            up,
+            fov,
+            horizontal_fov: None,
+            aspect_ratio: None,
+            roll: 0.0,
+        }
+    }
+
+    //muta camera pastrand directia in care priveste
+    pub fn move_to(&mut self, eye: Vector3) {
+        self.target += eye - self.eye;
+        self.eye = eye;
+    }
+
+    pub fn basis(&self) -> CameraBasis {
+        let forward = (self.target - self.eye).normalize();
+        let mut right = forward.cross(&self.up).normalize();
+        if right.norm() < 0.5 {
+            //up paralel cu directia de privire, alegem alta axa
+            let fallback = if forward.y.abs() < 0.9 {
+                default_up()
+            } else {
+                Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: -1.0,
+                }
+            };
+            right = forward.cross(&fallback).normalize();
+        }
+        let up = right.cross(&forward);
+
+        let (sin_roll, cos_roll) = self.roll.to_radians().sin_cos();
+        CameraBasis {
+            right: right * cos_roll + up * sin_roll,
+            up: up * cos_roll - right * sin_roll,
+            forward,
+        }
+    }
+
+    //jumatatile latimii si inaltimii senzorului aflat la distanta 1 de eye
+    pub fn sensor_extent(&self, width: u32, height: u32) -> (f64, f64) {
+        let half_height = (self.fov.to_radians() / 2.0).tan();
+        let half_width = match self.horizontal_fov {
+            Some(horizontal_fov) => (horizontal_fov.to_radians() / 2.0).tan(),
+            None => {
+                let aspect_ratio = self.aspect_ratio.unwrap_or(width as f64 / height as f64);
+                half_height * aspect_ratio
+            }
+        };
+        (half_width, half_height)
+    }
+
+    //pixel_x, pixel_y in coordonate de pixel, (x + 0.5, y + 0.5) e centrul pixelului (x, y)
+    pub fn create_ray(&self, pixel_x: f64, pixel_y: f64, width: u32, height: u32) -> Ray {
+        let basis = self.basis();
+        let (half_width, half_height) = self.sensor_extent(width, height);
+        let direction = |pixel_x: f64, pixel_y: f64| {
+            let x_sensor = ((pixel_x / width as f64) * 2.0 - 1.0) * half_width;
+            let y_sensor = (1.0 - (pixel_y / height as f64) * 2.0) * half_height; // y poz e in jos
+            (basis.forward + basis.right * x_sensor + basis.up * y_sensor).normalize()
+        };
+
+        Ray {
+            origin: self.eye,
+            direction: direction(pixel_x, pixel_y),
+            differential: Some(RayDifferential {
+                dx_origin: self.eye,
+                dx_direction: direction(pixel_x + 1.0, pixel_y),
+                dy_origin: self.eye,
+                dy_direction: direction(pixel_x, pixel_y + 1.0),
+            }),
+        }
+    }
+}
+
+#[test]
+fn test_default_camera_matches_old_prime_rays() {
+    let camera = Camera::new(Vector3::zero(), 100.0);
+    let (width, height) = (1200, 900);
+    let fov_fix = (100.0f64.to_radians() / 2.0).tan();
+    let aspect_ratio = width as f64 / height as f64;
+
+    for (x, y) in [(0, 0), (600, 450), (1199, 899), (17, 803)] {
+        let px = x as f64 + 0.5;
+        let py = y as f64 + 0.5;
+        let old = Vector3 {
+            x: ((px / width as f64) * 2.0 - 1.0) * aspect_ratio * fov_fix,
+            y: (1.0 - (py / height as f64) * 2.0) * fov_fix,
+            z: -1.0,
+        }
+        .normalize();
+        let ray = camera.create_ray(px, py, width, height);
+        assert!((ray.direction - old).norm() < 1e-12);
+    }
+
+    let mut looking_down = Camera::look_at(
+        Vector3 {
+            x: 0.0,
+            y: 10.0,
+            z: 0.0,
+        },
+        Vector3::zero(),
+        default_up(),
+        60.0,
+    );
+    looking_down.roll = 90.0;
+    let center = looking_down.create_ray(50.0, 50.0, 100, 100);
+    assert!((center.direction.y + 1.0).abs() < 1e-12);
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+//toate erorile de incarcare/salvare ale scenei (json, obj/mtl, texturi, mesh-uri)
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    //line si column incep de la 1
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    MissingTexture {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+    Validation(String),
+    Serialize(serde_json::Error),
+}
+
+impl SceneError {
+    pub(crate) fn from_json(path: PathBuf, err: serde_json::Error) -> SceneError {
+        SceneError::Parse {
+            path,
+            line: err.line(),
+            column: err.column(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SceneError::Io {
+                ref path,
+                ref source,
+            } => write!(f, "unable to access {:?}: {}", path, source),
+            SceneError::Parse {
+                ref path,
+                line,
+                column,
+                ref message,
+            } => write!(f, "{:?}:{}:{}: {}", path, line, column, message),
+            SceneError::MissingTexture {
+                ref path,
+                ref source,
+            } => write!(f, "unable to open texture {:?}: {}", path, source),
+            SceneError::Validation(ref message) => write!(f, "invalid scene: {}", message),
+            SceneError::Serialize(ref err) => write!(f, "unable to serialize scene: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            SceneError::Io { ref source, .. } => Some(source),
+            SceneError::MissingTexture { ref source, .. } => Some(source),
+            SceneError::Serialize(ref err) => Some(err),
+            SceneError::Parse { .. } | SceneError::Validation(_) => None,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use crate::pathtracer::{cosine_sample_hemisphere, PathTracer};
+use crate::render::{Intersectable, Ray};
+use crate::sampling::Sampler;
+use crate::scenedata::{Color, Scene, SurfaceType, SHADOW_BIAS};
+use serde::{Deserialize, Serialize};
+
+//calculeaza culoarea unei raze de camera; render_pixel apeleaza integratorul scenei
+//pentru fiecare sample. implementari proprii se seteaza cu SceneManager::set_integrator
+pub trait Integrator: Send + Sync {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut Sampler) -> Color;
+}
+
+//integratorii din json; Whitted e comportamentul initial al raytracer-ului
+#[derive(Serialize, Deserialize, Default)]
+pub enum RenderMode {
+    //lumina directa plus reflexii si refractii recursive, pana la max_recursion_depth
+    #[default]
+    #[serde(alias = "Direct")]
+    Whitted,
+    //doar lumina directa in primul punct lovit, fara raze secundare
+    DirectLighting,
+    AmbientOcclusion(AmbientOcclusion),
+    //normala primului punct lovit, mapata din [-1, 1] in [0, 1]
+    Normals,
+    PathTracing(PathTracer),
+}
+
+impl RenderMode {
+    pub fn integrator(&self) -> &dyn Integrator {
+        match *self {
+            RenderMode::Whitted => &Whitted,
+            RenderMode::DirectLighting => &DirectLighting,
+            RenderMode::AmbientOcclusion(ref ambient_occlusion) => ambient_occlusion,
+            RenderMode::Normals => &Normals,
+            RenderMode::PathTracing(ref path_tracer) => path_tracer,
+        }
+    }
+}
+
+pub struct Whitted;
+
+impl Integrator for Whitted {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut Sampler) -> Color {
+        scene.cast_ray(ray, 0, sampler)
+    }
+}
+
+pub struct DirectLighting;
+
+impl Integrator for DirectLighting {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut Sampler) -> Color {
+        match scene.closest_hit(ray) {
+            Some(intersection) => {
+                let hit = intersection.surface_point(ray);
+                let object = intersection.object;
+                let normal = object.surface_normal(&hit);
+                let color = match *object.surface() {
+                    SurfaceType::Conductor { .. }
+                    | SurfaceType::Dielectric { .. }
+                    | SurfaceType::Principled { .. } => {
+                        scene.shade_bsdf(ray, object, &hit, &normal, sampler)
+                    }
+                    _ => scene.shade_diffuse(
+                        ray,
+                        object,
+                        &hit,
+                        &normal.face_forward(&ray.direction),
+                        sampler,
+                    ),
+                };
+                match object.material().emission {
+                    Some(ref emission) => (color + emission.radiance()).clamp(),
+                    None => color.clamp(),
+                }
+            }
+            None => scene.background.color(&ray.direction),
+        }
+    }
+}
+
+//cat din emisfera de deasupra punctului nu e acoperita de alte obiecte (alb = deschis)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmbientOcclusion {
+    #[serde(default = "default_occlusion_samples")]
+    pub samples: u32,
+    //obiectele mai departe de atat nu mai ocluzeaza; None = oricat de departe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_distance: Option<f64>,
+}
+
+fn default_occlusion_samples() -> u32 {
+    16
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut Sampler) -> Color {
+        let intersection = match scene.closest_hit(ray) {
+            Some(intersection) => intersection,
+            None => return Color::gray(1.0),
+        };
+        let hit = intersection.surface_point(ray);
+        let mut normal = intersection.object.surface_normal(&hit);
+        if normal.dot(&ray.direction) > 0.0 {
+            normal = -normal;
+        }
+        let origin = hit.point + normal * SHADOW_BIAS;
+
+        let samples = self.samples.max(1);
+        let open = (0..samples)
+            .filter(|_| {
+                let occlusion_ray = Ray {
+                    origin,
+                    direction: cosine_sample_hemisphere(&normal, sampler),
+                    differential: None,
+                };
+                match scene.closest_hit(&occlusion_ray) {
+                    Some(hit) => self.max_distance.is_some_and(|max| hit.distance > max),
+                    None => true,
+                }
+            })
+            .count();
+        Color::gray(open as f32 / samples as f32)
+    }
+}
+
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, scene: &Scene, ray: &Ray, _sampler: &mut Sampler) -> Color {
+        match scene.closest_hit(ray) {
+            Some(intersection) => {
+                let normal = intersection
+                    .object
+                    .surface_normal(&intersection.surface_point(ray));
+                Color {
+                    red: ((normal.x + 1.0) / 2.0) as f32,
+                    green: ((normal.y + 1.0) / 2.0) as f32,
+                    blue: ((normal.z + 1.0) / 2.0) as f32,
+                }
+            }
+            None => Color::black(),
+        }
+    }
+}
+
+#[test]
+fn test_integrators_from_json_and_api() {
+    use crate::scenemanager::SceneManager;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    let json = r#"{
+        "camera": {"eye": {"x": 0.0, "y": 0.0, "z": 0.0}, "target": {"x": 0.0, "y": 0.0, "z": -1.0}, "fov": 60.0},
+        "width": 1, "height": 1, "lights": [],
+        "render_mode": "Normals",
+        "objects": [{"Sphere": {"center": {"x": 0.0, "y": 0.0, "z": -5.0}, "radius": 1.0,
+            "material": {"coloration": {"Color": {"red": 1.0, "green": 1.0, "blue": 1.0}}, "albedo": 1.0}}}]
+    }"#;
+    let scene: Scene = serde_json::from_str(json).unwrap();
+    let mut scenemgr = SceneManager::new_from_scene(scene, Path::new("")).unwrap();
+    //raza din centru loveste sfera in punctul cu normala +z
+    let normal = scenemgr.scene.render_pixel(0, 0);
+    assert_eq!((normal.red, normal.green, normal.blue), (0.5, 0.5, 1.0));
+
+    //o sfera singura nu se ocluzeaza pe ea insasi
+    scenemgr
+        .set_render_mode(serde_json::from_str(r#"{"AmbientOcclusion": {"samples": 8}}"#).unwrap());
+    assert_eq!(scenemgr.scene.render_pixel(0, 0).red, 1.0);
+
+    struct Constant;
+    impl Integrator for Constant {
+        fn radiance(&self, _scene: &Scene, _ray: &Ray, _sampler: &mut Sampler) -> Color {
+            Color::gray(0.25)
+        }
+    }
+    scenemgr.set_integrator(Arc::new(Constant));
+    assert_eq!(scenemgr.scene.render_pixel(0, 0).green, 0.25);
+    //integratorul propriu nu ajunge in json
+    let saved = serde_json::to_string(&scenemgr.scene).unwrap();
+    assert!(saved.contains("AmbientOcclusion"));
+}
@@ -1,26 +1,46 @@
+pub mod background;
+pub mod bsdf;
+pub mod bvh;
+pub mod camera;
+pub mod error;
+pub mod integrator;
+pub mod obj;
+pub mod parallel;
+pub mod pathtracer;
+pub mod procedural;
 pub mod render;
+pub mod sampling;
 pub mod scenedata;
 pub mod scenemanager;
+pub mod texture;
+pub mod transform;
 pub mod vector3;
 
-use image::{DynamicImage, GenericImage, Rgba};
-use minifb::{Key, Window, WindowOptions};
-use render::Ray;
+use error::SceneError;
+use image::{DynamicImage, GenericImage};
+use minifb::{Key, Window};
+use scenedata::{Color, Scene};
+use scenemanager::SceneManager;
+
+#[cfg(test)]
+use minifb::WindowOptions;
+#[cfg(test)]
 use scenedata::{
-    Color, Coloration, Cube, Element, Light, Material, Plane, PointLight, Scene, Sphere, Texture,
+    Coloration, Cube, Element, Light, Material, Plane, PointLight, Sphere, SurfaceType, Texture,
 };
-use scenemanager::SceneManager;
+#[cfg(test)]
 use std::path::PathBuf;
-use vector3::Vector3;
+#[cfg(test)]
+use vector3::{Normal3, Vector3};
 
 #[test]
 fn test_render() {
     //in the future , interactable scene manager , moving camera etc
 
-    let rainbowtexture = Texture {
-        path: PathBuf::from("resources/rainbowtexture.png"),
-        texture: image::open("resources/rainbowtexture.png").expect("failed to open texture"),
-    };
+    let rainbowtexture = Texture::from_image(
+        PathBuf::from("resources/rainbowtexture.png"),
+        image::open("resources/rainbowtexture.png").expect("failed to open texture"),
+    );
     /*
         let scene = Scene {
             width: 1200,
@@ -184,6 +204,9 @@ fn test_render() {
                     blue: 0.0,
                 }),
                 albedo: 1.00,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
             },
         }),
         Element::Sphere(Sphere {
@@ -200,6 +223,9 @@ fn test_render() {
                     blue: 0.0,
                 }),
                 albedo: 0.18,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
             },
         }),
         Element::Sphere(Sphere {
@@ -216,6 +242,9 @@ fn test_render() {
                     blue: 0.0,
                 }),
                 albedo: 0.98,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
             },
         }),
         Element::Cube(Cube {
@@ -228,6 +257,9 @@ fn test_render() {
             material: Material {
                 coloration: Coloration::Texture(rainbowtexture),
                 albedo: 1.0,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
             },
         }),
         Element::Plane(Plane {
@@ -236,9 +268,9 @@ fn test_render() {
                 y: -3.0,
                 z: -4.0,
             },
-            normal: Vector3 {
+            normal: Normal3 {
                 x: 0.0,
-                y: -1.0,
+                y: 1.0,
                 z: 0.0,
             },
             material: Material {
@@ -248,6 +280,9 @@ fn test_render() {
                     blue: 0.12,
                 }),
                 albedo: 1.0,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
             },
         }),
         Element::Plane(Plane {
@@ -256,10 +291,10 @@ fn test_render() {
                 y: -3.0,
                 z: -20.0,
             },
-            normal: Vector3 {
+            normal: Normal3 {
                 x: 0.0,
                 y: 0.0,
-                z: -1.0,
+                z: 1.0,
             },
             material: Material {
                 coloration: Coloration::Color(Color {
@@ -268,11 +303,14 @@ fn test_render() {
                     blue: 0.5,
                 }),
                 albedo: 1.0,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
             },
         }),
     ];
 
-    let mut scenemgr = SceneManager::new(
+    let scenemgr = SceneManager::new(
         1200,
         900,
         100.0,
@@ -284,14 +322,19 @@ fn test_render() {
         lights,
         objects,
     );
-    scenemgr.save_to_json("resources/scene1.json");
+    //scena salvata ajunge in directorul temporar, nu in resources/
+    let scene_path = std::env::temp_dir().join("scene1.json");
+    let scene_path = scene_path.to_str().expect("temp dir is not valid utf-8");
+    scenemgr
+        .save_to_json(scene_path)
+        .expect("failed to save scene");
 
     //let scenemgr =
-    //    SceneManager::load_from_json("resources/scene1.json").expect("failed to load scene");
+    //    SceneManager::load_from_json(scene_path).expect("failed to load scene");
 
     //save scene to json
     let scene = scenemgr.scene;
-    let img = render(&scene);
+    let img = render(&scene).expect("failed to render scene");
 
     assert_eq!(scene.width, img.width());
     assert_eq!(scene.height, img.height());
@@ -309,7 +352,7 @@ fn test_render() {
     });
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        buffer = image_to_buffer(&render(&scene));
+        buffer = image_to_buffer(&render(&scene).expect("failed to render scene"));
         window
             .update_with_buffer(&buffer, scene.width as usize, scene.height as usize)
             .unwrap();
@@ -318,13 +361,16 @@ fn test_render() {
     }
 }
 
+#[allow(dead_code)]
 fn handle_input(window: &mut Window, scenemgr: &mut SceneManager) {
     if window.is_key_down(Key::W) {
-        scenemgr.scene.ray_origin.z += 0.1;
+        let mut eye = scenemgr.scene.camera.eye;
+        eye.z += 0.1;
+        scenemgr.set_ray_origin(eye);
     }
 }
 
-fn image_to_buffer(img: &DynamicImage) -> Vec<u32> {
+pub fn image_to_buffer(img: &DynamicImage) -> Vec<u32> {
     let rgb_image = img.to_rgb8();
     let mut buffer = vec![0; (rgb_image.width() * rgb_image.height()) as usize];
 
@@ -338,20 +384,15 @@ fn image_to_buffer(img: &DynamicImage) -> Vec<u32> {
     buffer
 }
 
-pub fn render(scene: &Scene) -> DynamicImage {
+//o scena fara load_assets e o eroare, nu o imagine neagra
+pub fn render(scene: &Scene) -> Result<DynamicImage, SceneError> {
+    scene.check_loaded()?;
     let mut image = DynamicImage::new_rgb8(scene.width, scene.height);
-    let black = Rgba([0, 0, 0, 255]);
 
     for x in 0..scene.width {
         for y in 0..scene.height {
-            let ray = Ray::create_prime(x, y, scene);
-            let i = scene.trace(&ray);
-            if let Some(intersection) = i {
-                image.put_pixel(x, y, Color::to_rgb(&scene.get_color(&ray, &intersection)));
-            } else {
-                image.put_pixel(x, y, black);
-            }
+            image.put_pixel(x, y, Color::to_rgb(&scene.render_pixel(x, y)));
         }
     }
-    image
+    Ok(image)
 }
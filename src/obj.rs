@@ -0,0 +1,517 @@
+use crate::error::SceneError;
+use crate::scenedata::{
+    BlinnPhong, Color, Coloration, Element, Emission, Material, Mesh, MeshData, SurfaceType,
+    Texture,
+};
+use crate::transform::Transform;
+use crate::vector3::{Normal3, Point3, Vector3};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+struct ObjMaterial {
+    diffuse: Color,
+    diffuse_map: Option<PathBuf>,
+    dissolve: f32,
+    index_of_refraction: f32,
+    //extensia PBR a formatului mtl (Pm, Pr), scrisa de exporterele Blender si 3ds Max
+    metallic: Option<f32>,
+    roughness: Option<f32>,
+    specular: Color,
+    specular_exponent: f32,
+    emission: Color,
+}
+
+impl Default for ObjMaterial {
+    fn default() -> Self {
+        ObjMaterial {
+            diffuse: Color {
+                red: 0.8,
+                green: 0.8,
+                blue: 0.8,
+            },
+            diffuse_map: None,
+            dissolve: 1.0,
+            index_of_refraction: 1.5,
+            metallic: None,
+            roughness: None,
+            specular: Color::black(),
+            specular_exponent: 10.0,
+            emission: Color::black(),
+        }
+    }
+}
+
+//un grup de fete care folosesc acelasi material, devine un Mesh separat
+#[derive(Default)]
+struct FaceGroup {
+    material: Option<String>,
+    data: MeshData,
+    //(v, vt, vn) din fisier -> index in data.positions
+    vertex_map: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    missing_normals: bool,
+    has_uvs: bool,
+}
+
+struct ObjParser<'a> {
+    path: &'a Path,
+    line: usize,
+    column: usize,
+}
+
+impl ObjParser<'_> {
+    //erorile indica inceputul instructiunii de pe linia curenta
+    fn error(&self, message: impl Into<String>) -> SceneError {
+        SceneError::Parse {
+            path: self.path.to_path_buf(),
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn start_line(&mut self, index: usize, line: &str) {
+        self.line = index + 1;
+        self.column = line.len() - line.trim_start().len() + 1;
+    }
+
+    fn floats<'b>(
+        &self,
+        parts: impl Iterator<Item = &'b str>,
+        min: usize,
+        max: usize,
+    ) -> Result<Vec<f64>, SceneError> {
+        let values = parts
+            .map(|p| {
+                p.parse::<f64>()
+                    .map_err(|_| self.error(format!("invalid number {:?}", p)))
+            })
+            .collect::<Result<Vec<f64>, SceneError>>()?;
+        if values.len() < min || values.len() > max {
+            return Err(self.error(format!(
+                "expected {} to {} numbers, found {}",
+                min,
+                max,
+                values.len()
+            )));
+        }
+        Ok(values)
+    }
+
+    //indicii obj incep de la 1, cei negativi sunt relativi la finalul listei
+    fn index(&self, value: &str, count: usize, kind: &str) -> Result<usize, SceneError> {
+        let index: i64 = value
+            .parse()
+            .map_err(|_| self.error(format!("invalid {} index {:?}", kind, value)))?;
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!(
+                "{} index {} out of range (have {})",
+                kind, index, count
+            )));
+        }
+        Ok(resolved as usize)
+    }
+}
+
+pub fn load_obj(path: &Path, transform: Option<&Transform>) -> Result<Vec<Element>, SceneError> {
+    let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let transform = transform.cloned().unwrap_or_else(Transform::identity);
+
+    let mut parser = ObjParser {
+        path,
+        line: 0,
+        column: 0,
+    };
+    let mut positions: Vec<Vector3> = Vec::new();
+    let mut normals: Vec<Vector3> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut materials: HashMap<String, ObjMaterial> = HashMap::new();
+    let mut groups: Vec<FaceGroup> = vec![FaceGroup::default()];
+
+    for (line_index, line) in source.lines().enumerate() {
+        parser.start_line(line_index, line);
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+
+        match keyword {
+            "v" => {
+                let v = parser.floats(parts, 3, 4)?;
+                positions.push(Vector3::from(transform.point_to_world(&Point3 {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                })));
+            }
+            "vn" => {
+                let n = parser.floats(parts, 3, 3)?;
+                normals.push(Vector3::from(transform.normal_to_world(&Normal3 {
+                    x: n[0],
+                    y: n[1],
+                    z: n[2],
+                })));
+            }
+            "vt" => {
+                let t = parser.floats(parts, 1, 3)?;
+                //in obj v = 0 e jos, in imagine y = 0 e sus
+                uvs.push([t[0] as f32, 1.0 - *t.get(1).unwrap_or(&0.0) as f32]);
+            }
+            "f" => {
+                let mut corners = Vec::new();
+                for corner in parts {
+                    let mut fields = corner.split('/');
+                    let v = parser.index(fields.next().unwrap_or(""), positions.len(), "vertex")?;
+                    let vt = match fields.next() {
+                        Some("") | None => None,
+                        Some(t) => Some(parser.index(t, uvs.len(), "texture")?),
+                    };
+                    let vn = match fields.next() {
+                        Some("") | None => None,
+                        Some(n) => Some(parser.index(n, normals.len(), "normal")?),
+                    };
+                    corners.push((v, vt, vn));
+                }
+                if corners.len() < 3 {
+                    return Err(parser.error("face needs at least 3 vertices"));
+                }
+
+                let group = groups.last_mut().unwrap();
+                let indices: Vec<usize> = corners
+                    .iter()
+                    .map(|&key| group.vertex(key, &positions, &normals, &uvs))
+                    .collect();
+                //poligoanele sunt impartite in evantai de triunghiuri
+                for i in 1..indices.len() - 1 {
+                    group
+                        .data
+                        .indices
+                        .push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            "usemtl" => {
+                let name = parts.collect::<Vec<&str>>().join(" ");
+                if name.is_empty() {
+                    return Err(parser.error("usemtl needs a material name"));
+                }
+                //mtllib trebuie sa vina inainte de usemtl
+                if !materials.contains_key(&name) {
+                    return Err(parser.error(format!("unknown material {:?}", name)));
+                }
+                groups.push(FaceGroup {
+                    material: Some(name),
+                    ..FaceGroup::default()
+                });
+            }
+            "mtllib" => {
+                let file = parts.collect::<Vec<&str>>().join(" ");
+                if file.is_empty() {
+                    return Err(parser.error("mtllib needs a file name"));
+                }
+                materials.extend(load_mtl(&base_dir.join(file))?);
+            }
+            //grupuri, smoothing, linii, curbe etc. nu au echivalent in scena
+            _ => {}
+        }
+    }
+
+    //mai multe materiale cu aceeasi imagine impart o singura textura
+    let mut texture_cache: HashMap<PathBuf, Texture> = HashMap::new();
+    let mut elements = Vec::new();
+    for mut group in groups.into_iter().filter(|g| !g.data.indices.is_empty()) {
+        if group.missing_normals {
+            group.data.normals.clear();
+        }
+        if !group.has_uvs {
+            group.data.uvs.clear();
+        }
+
+        let obj_material = group.material.as_ref().and_then(|name| materials.get(name));
+        let material = match obj_material {
+            Some(m) => build_material(m, &mut texture_cache)?,
+            None => build_material(&ObjMaterial::default(), &mut texture_cache)?,
+        };
+        let mesh = Mesh::new(group.data, material)?;
+        elements.push(Element::Mesh(mesh));
+    }
+    Ok(elements)
+}
+
+impl FaceGroup {
+    fn vertex(
+        &mut self,
+        key: (usize, Option<usize>, Option<usize>),
+        positions: &[Vector3],
+        normals: &[Vector3],
+        uvs: &[[f32; 2]],
+    ) -> usize {
+        if let Some(&index) = self.vertex_map.get(&key) {
+            return index;
+        }
+
+        let (v, vt, vn) = key;
+        let index = self.data.positions.len();
+        self.data.positions.push(positions[v]);
+        match vn {
+            Some(n) => self.data.normals.push(normals[n]),
+            None => {
+                self.missing_normals = true;
+                self.data.normals.push(Vector3::zero());
+            }
+        }
+        match vt {
+            Some(t) => {
+                self.has_uvs = true;
+                self.data.uvs.push(uvs[t]);
+            }
+            None => self.data.uvs.push([0.0, 0.0]),
+        }
+        self.vertex_map.insert(key, index);
+        index
+    }
+}
+
+fn build_material(
+    obj_material: &ObjMaterial,
+    texture_cache: &mut HashMap<PathBuf, Texture>,
+) -> Result<Material, SceneError> {
+    let coloration = match obj_material.diffuse_map {
+        Some(ref path) => {
+            if !texture_cache.contains_key(path) {
+                texture_cache.insert(path.clone(), Texture::load(path.clone())?);
+            }
+            Coloration::Texture(texture_cache[path].clone())
+        }
+        None => Coloration::Color(obj_material.diffuse.clone()),
+    };
+
+    let surface = if obj_material.dissolve < 1.0 {
+        SurfaceType::Refractive {
+            index: obj_material.index_of_refraction,
+            transparency: 1.0 - obj_material.dissolve,
+        }
+    } else if obj_material.metallic.is_some() || obj_material.roughness.is_some() {
+        SurfaceType::Principled {
+            metallic: obj_material.metallic.unwrap_or(0.0),
+            roughness: obj_material.roughness.unwrap_or(0.5),
+            specular: 0.5,
+        }
+    } else {
+        SurfaceType::Diffuse
+    };
+
+    //Ks negru (implicit) = fara highlight
+    let specular = &obj_material.specular;
+    let highlight = if specular.red > 0.0 || specular.green > 0.0 || specular.blue > 0.0 {
+        Some(BlinnPhong {
+            color: specular.clone(),
+            shininess: obj_material.specular_exponent,
+            weight: 1.0,
+        })
+    } else {
+        None
+    };
+
+    let emission = &obj_material.emission;
+    let emission = if emission.red > 0.0 || emission.green > 0.0 || emission.blue > 0.0 {
+        Some(Emission {
+            color: emission.clone(),
+            strength: 1.0,
+        })
+    } else {
+        None
+    };
+
+    Ok(Material {
+        coloration,
+        albedo: 1.0,
+        surface,
+        specular: highlight,
+        emission,
+    })
+}
+
+fn load_mtl(path: &Path) -> Result<HashMap<String, ObjMaterial>, SceneError> {
+    let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut parser = ObjParser {
+        path,
+        line: 0,
+        column: 0,
+    };
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, ObjMaterial)> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        parser.start_line(line_index, line);
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            let name = parts.collect::<Vec<&str>>().join(" ");
+            if name.is_empty() {
+                return Err(parser.error("newmtl needs a material name"));
+            }
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((name, ObjMaterial::default()));
+            continue;
+        }
+
+        let Some((_, ref mut material)) = current else {
+            return Err(parser.error(format!("{:?} before any newmtl", keyword)));
+        };
+        match keyword {
+            "Kd" => {
+                let c = parser.floats(parts, 3, 3)?;
+                material.diffuse = Color {
+                    red: c[0] as f32,
+                    green: c[1] as f32,
+                    blue: c[2] as f32,
+                };
+            }
+            "Ks" => {
+                let c = parser.floats(parts, 3, 3)?;
+                material.specular = Color {
+                    red: c[0] as f32,
+                    green: c[1] as f32,
+                    blue: c[2] as f32,
+                };
+            }
+            "Ke" => {
+                let c = parser.floats(parts, 3, 3)?;
+                material.emission = Color {
+                    red: c[0] as f32,
+                    green: c[1] as f32,
+                    blue: c[2] as f32,
+                };
+            }
+            "Ns" => material.specular_exponent = parser.floats(parts, 1, 1)?[0] as f32,
+            "map_Kd" => {
+                //optiunile (-s, -o ...) nu sunt suportate, luam doar ultimul token ca fisier
+                let file = parts
+                    .last()
+                    .ok_or_else(|| parser.error("map_Kd needs a file name"))?;
+                material.diffuse_map = Some(base_dir.join(file));
+            }
+            "d" => material.dissolve = parser.floats(parts, 1, 1)?[0] as f32,
+            "Tr" => material.dissolve = 1.0 - parser.floats(parts, 1, 1)?[0] as f32,
+            "Ni" => material.index_of_refraction = parser.floats(parts, 1, 1)?[0] as f32,
+            "Pm" => material.metallic = Some(parser.floats(parts, 1, 1)?[0] as f32),
+            "Pr" => material.roughness = Some(parser.floats(parts, 1, 1)?[0] as f32),
+            //restul proprietatilor nu au echivalent in Material
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+#[test]
+fn test_load_obj_with_mtl() {
+    let dir = std::env::temp_dir().join(format!("raytracer_obj_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("quad.mtl"),
+        "newmtl red\nKd 1.0 0.0 0.0\nNs 10\n\nnewmtl glass\nKd 1 1 1\nd 0.1\nNi 1.5\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("quad.obj"),
+        "mtllib quad.mtl\n\
+         v -1 -1 0\nv 1 -1 0\nv 1 1 0\nv -1 1 0\n\
+         vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+         usemtl red\nf 1/1 2/2 3/3 4/4\n\
+         usemtl glass\nf -4 -2 -1\n",
+    )
+    .unwrap();
+    fs::write(dir.join("broken.obj"), "v 0 0 0\nv 1 0 0\nf 1 2 7\n").unwrap();
+    fs::write(
+        dir.join("unknown.obj"),
+        "mtllib quad.mtl\nv 0 0 0\n  usemtl blue\n",
+    )
+    .unwrap();
+    image::RgbImage::new(2, 2)
+        .save(dir.join("wood.png"))
+        .unwrap();
+    fs::write(
+        dir.join("wood.mtl"),
+        "newmtl a\nmap_Kd wood.png\nnewmtl b\nmap_Kd wood.png\nKs 1 1 1\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("wood.obj"),
+        "mtllib wood.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl a\nf 1 2 3\nusemtl b\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let transform = Transform::new(
+        Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -5.0,
+        },
+        Vector3::zero(),
+        Vector3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        },
+    )
+    .unwrap();
+    let elements = load_obj(&dir.join("quad.obj"), Some(&transform)).unwrap();
+    assert_eq!(elements.len(), 2);
+    match elements[0] {
+        Element::Mesh(ref m) => {
+            assert_eq!(m.data.indices.len(), 2);
+            assert_eq!(m.data.positions[0].z, -5.0);
+            assert!(matches!(m.material.coloration, Coloration::Color(ref c) if c.red == 1.0));
+        }
+        _ => panic!("expected a mesh"),
+    }
+    assert!(matches!(
+        elements[1].surface(),
+        SurfaceType::Refractive { .. }
+    ));
+
+    match load_obj(&dir.join("broken.obj"), None) {
+        Err(SceneError::Parse { line, column, .. }) => assert_eq!((line, column), (3, 1)),
+        _ => panic!("expected a parse error"),
+    }
+    match load_obj(&dir.join("unknown.obj"), None) {
+        Err(SceneError::Parse { line, column, .. }) => assert_eq!((line, column), (3, 3)),
+        _ => panic!("expected an unknown material error"),
+    }
+
+    //aceeasi imagine in doua materiale e citita o singura data
+    let wood = load_obj(&dir.join("wood.obj"), None).unwrap();
+    let texture = |element: &Element| match element.material().coloration {
+        Coloration::Texture(ref texture) => std::sync::Arc::clone(&texture.mips),
+        _ => panic!("expected a texture"),
+    };
+    assert!(std::sync::Arc::ptr_eq(
+        &texture(&wood[0]),
+        &texture(&wood[1])
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
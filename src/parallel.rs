@@ -0,0 +1,154 @@
+use crate::error::SceneError;
+use crate::scenedata::{Color, Scene};
+use image::{DynamicImage, GenericImage, Rgba};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+pub const TILE_SIZE: u32 = 32;
+
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+//imaginea e impartita in tile-uri de TILE_SIZE x TILE_SIZE, fiecare thread ia urmatorul
+//tile liber; fiecare pixel depinde doar de coordonatele lui, deci rezultatul e acelasi
+//indiferent de numarul de thread-uri
+//thread_count = 0 foloseste cate core-uri are masina
+pub fn render_parallel(scene: &Scene, thread_count: usize) -> Result<DynamicImage, SceneError> {
+    scene.check_loaded()?;
+    let tiles = split_tiles(scene.width, scene.height);
+    let thread_count = resolve_thread_count(thread_count).min(tiles.len().max(1));
+
+    let next_tile = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<(usize, Vec<Rgba<u8>>)>();
+    let mut image = DynamicImage::new_rgb8(scene.width, scene.height);
+
+    thread::scope(|s| {
+        for _ in 0..thread_count {
+            let sender = sender.clone();
+            let tiles = &tiles;
+            let next_tile = &next_tile;
+            s.spawn(move || loop {
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                let Some(tile) = tiles.get(index) else {
+                    break;
+                };
+                let pixels = render_tile(scene, tile);
+                if sender.send((index, pixels)).is_err() {
+                    break;
+                }
+            });
+        }
+        //thread-ul curent copiaza tile-urile in imagine pe masura ce sunt gata, ca in canal
+        //sa nu se adune tot cadrul; bucla se termina cand toti workerii au iesit
+        drop(sender);
+        for (index, pixels) in receiver {
+            let tile = &tiles[index];
+            for (i, pixel) in pixels.into_iter().enumerate() {
+                let x = tile.x + i as u32 % tile.width;
+                let y = tile.y + i as u32 / tile.width;
+                image.put_pixel(x, y, pixel);
+            }
+        }
+    });
+    Ok(image)
+}
+
+pub fn resolve_thread_count(thread_count: usize) -> usize {
+    if thread_count > 0 {
+        thread_count
+    } else {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+}
+
+fn split_tiles(width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(TILE_SIZE as usize) {
+        for x in (0..width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+fn render_tile(scene: &Scene, tile: &Tile) -> Vec<Rgba<u8>> {
+    let mut pixels = Vec::with_capacity((tile.width * tile.height) as usize);
+    for y in tile.y..tile.y + tile.height {
+        for x in tile.x..tile.x + tile.width {
+            pixels.push(Color::to_rgb(&scene.render_pixel(x, y)));
+        }
+    }
+    pixels
+}
+
+//scena e citita din mai multe thread-uri in acelasi timp
+const _: () = {
+    const fn assert_sync<T: Sync>() {}
+    assert_sync::<Scene>();
+    assert_sync::<crate::scenedata::Texture>();
+};
+
+#[test]
+fn test_parallel_render_matches_serial() {
+    use crate::sampling::{ReconstructionFilter, Sampling};
+    use crate::scenedata::{
+        test_material, test_scene, test_sphere, Light, PointLight, SurfaceType,
+    };
+    use crate::vector3::Vector3;
+
+    let mut scenemgr = test_scene(
+        75,
+        50,
+        vec![Light::Point(PointLight {
+            point: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            color: Color::gray(1.0),
+            intensity: 100.0,
+        })],
+        vec![test_sphere(
+            Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: -4.0,
+            },
+            1.5,
+            test_material(
+                Color {
+                    red: 0.8,
+                    green: 0.3,
+                    blue: 0.1,
+                },
+                1.0,
+                SurfaceType::Reflective { reflectivity: 0.3 },
+            ),
+        )],
+    );
+
+    scenemgr.set_sampling(Sampling {
+        samples_per_pixel: 4,
+        filter: ReconstructionFilter::Mitchell,
+        jitter: true,
+        seed: 42,
+    });
+
+    let serial = crate::render(&scenemgr.scene).unwrap().to_rgb8();
+    for threads in [1, 3, 8] {
+        let parallel = render_parallel(&scenemgr.scene, threads).unwrap().to_rgb8();
+        assert_eq!(serial.as_raw(), parallel.as_raw());
+    }
+}
@@ -0,0 +1,479 @@
+use crate::background::Background;
+use crate::bsdf::Bsdf;
+use crate::integrator::Integrator;
+use crate::render::{fresnel, Intersectable, Ray};
+use crate::sampling::Sampler;
+use crate::scenedata::{
+    orthonormal_basis, sphere_cone_cos, AreaLight, AreaShape, Color, Light, Scene, SurfaceType,
+    LIGHT_SURFACE_EPSILON, SHADOW_BIAS,
+};
+use crate::vector3::Vector3;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+//path tracer monte carlo: directia urmatoare e aleasa cosinus-ponderat, iar la fiecare
+//punct difuz se trage si cate o raza spre fiecare lumina (next event estimation).
+//luminile de suprafata si harta de mediu sunt combinate cu multiple importance sampling.
+//luminile sunt evaluate ca in LightModel::Physical, fara termenul ambient: lumina
+//indirecta si fundalul il inlocuiesc
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathTracer {
+    #[serde(default = "default_max_bounces")]
+    pub max_bounces: u32,
+    //de la aceasta adancime drumurile cu contributie mica sunt oprite aleator
+    #[serde(default = "default_russian_roulette_depth")]
+    pub russian_roulette_depth: u32,
+}
+
+fn default_max_bounces() -> u32 {
+    8
+}
+
+fn default_russian_roulette_depth() -> u32 {
+    3
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer {
+            max_bounces: default_max_bounces(),
+            russian_roulette_depth: default_russian_roulette_depth(),
+        }
+    }
+}
+
+fn white() -> Color {
+    Color {
+        red: 1.0,
+        green: 1.0,
+        blue: 1.0,
+    }
+}
+
+fn max_component(color: &Color) -> f32 {
+    color.red.max(color.green).max(color.blue)
+}
+
+//euristica puterii (beta = 2) pentru doua strategii cu cate un sample
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
+//directie in emisfera lui normal, cu densitatea cos(theta) / pi
+pub fn cosine_sample_hemisphere(normal: &Vector3, sampler: &mut Sampler) -> Vector3 {
+    let (a, b) = (sampler.next_f64(), sampler.next_f64());
+    let r = a.sqrt();
+    let (sin, cos) = (2.0 * PI * b).sin_cos();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    (tangent * (r * cos) + bitangent * (r * sin) + *normal * (1.0 - a).max(0.0).sqrt()).normalize()
+}
+
+//densitatea, in unghi solid, cu care next event estimation alege punctul dat de pe lumina;
+//aceeasi si pentru punctele gasite de raze, ca ponderile MIS sa se potriveasca.
+//`part` e bucata formei pe care se afla punctul
+fn area_light_pdf(
+    light: &AreaLight,
+    part: &AreaShape,
+    from: &Vector3,
+    light_point: &Vector3,
+) -> f64 {
+    //sfera e esantionata uniform in conul sub care se vede, din interior deloc
+    if let AreaShape::Sphere { center, radius } = *part {
+        return match sphere_cone_cos(&center, radius, from) {
+            Some(cos_max) => 1.0 / (2.0 * PI * (1.0 - cos_max)),
+            None => 0.0,
+        };
+    }
+    let to_light = *light_point - *from;
+    let distance_squared = to_light.dot(&to_light);
+    //formele plate emit din ambele fete; bucatile unei forme compuse sunt alese
+    //proportional cu aria, deci punctul e uniform pe toata aria luminii
+    let cos_light = part.normal_at(light_point).dot(&to_light.normalize()).abs();
+    if cos_light <= 0.0 {
+        return 0.0;
+    }
+    distance_squared / (cos_light * light.shape.area())
+}
+
+//lumina generata dintr-un obiect emisiv pe care raza il loveste la distanta data;
+//None daca obiectul nu e esantionat de next event estimation
+fn emitter_at<'a>(
+    scene: &'a Scene,
+    ray: &Ray,
+    distance: f64,
+) -> Option<(&'a AreaLight, &'a AreaShape)> {
+    scene.emitters.iter().find_map(|light| match *light {
+        Light::Area(ref alight) => alight
+            .shape
+            .intersect_part(ray)
+            .filter(|(d, _)| (d - distance).abs() <= distance * LIGHT_SURFACE_EPSILON)
+            .map(|(_, part)| (alight, part)),
+        _ => None,
+    })
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, scene: &Scene, camera_ray: &Ray, sampler: &mut Sampler) -> Color {
+        let mut ray = Ray {
+            origin: camera_ray.origin,
+            direction: camera_ray.direction,
+            differential: camera_ray.differential,
+        };
+        let mut throughput = white();
+        let mut radiance = Color::black();
+        //None dupa camera si dupa reflexii perfecte: acolo nu s-a facut next event estimation
+        let mut last_bsdf_pdf: Option<f64> = None;
+        let mut last_point = ray.origin;
+
+        for bounce in 0..=self.max_bounces {
+            let hit = scene.closest_hit(&ray);
+            //luminile de suprafata vazute direct de camera doar daca sunt `visible`
+            let only_visible = bounce == 0;
+            if let Some((distance, light)) = scene.intersect_area_lights(&ray, only_visible) {
+                if hit.as_ref().is_none_or(|h| distance < h.distance) {
+                    let weight = match last_bsdf_pdf {
+                        Some(bsdf_pdf) => {
+                            let light_point = ray.origin + ray.direction * distance;
+                            power_heuristic(
+                                bsdf_pdf,
+                                area_light_pdf(light, &light.shape, &last_point, &light_point),
+                            )
+                        }
+                        None => 1.0,
+                    };
+                    radiance = radiance + throughput.clone() * light.radiance() * weight as f32;
+                    break;
+                }
+            }
+
+            let intersection = match hit {
+                Some(intersection) => intersection,
+                None => {
+                    let weight = match (&scene.background, last_bsdf_pdf) {
+                        (Background::Environment(ref environment), Some(bsdf_pdf))
+                            if environment.lighting =>
+                        {
+                            power_heuristic(bsdf_pdf, environment.pdf(&ray.direction))
+                        }
+                        _ => 1.0,
+                    };
+                    radiance = radiance
+                        + throughput.clone()
+                            * scene.background.color(&ray.direction)
+                            * weight as f32;
+                    break;
+                }
+            };
+
+            let object = intersection.object;
+            let hit = intersection.surface_point(&ray);
+            let point = hit.point;
+            let normal = object.surface_normal(&hit);
+            if let Some(ref emission) = object.material().emission {
+                let weight = match last_bsdf_pdf {
+                    Some(bsdf_pdf) => match emitter_at(scene, &ray, intersection.distance) {
+                        Some((light, part)) => power_heuristic(
+                            bsdf_pdf,
+                            area_light_pdf(light, part, &last_point, &point),
+                        ),
+                        None => 1.0,
+                    },
+                    None => 1.0,
+                };
+                radiance = radiance + throughput.clone() * emission.radiance() * weight as f32;
+            }
+            let surface_color = object.surface_color(&ray, &hit);
+
+            //alegem aleator una dintre componentele suprafetei, cu probabilitatea ponderii ei
+            let (specular_weight, transparency, index) = match *object.surface() {
+                SurfaceType::Diffuse
+                | SurfaceType::Conductor { .. }
+                | SurfaceType::Dielectric { .. }
+                | SurfaceType::Principled { .. } => (0.0, 0.0, 1.0),
+                SurfaceType::Reflective { reflectivity } => (reflectivity, 0.0, 1.0),
+                SurfaceType::Refractive {
+                    index,
+                    transparency,
+                } => (0.0, transparency, index as f64),
+            };
+            let choice = sampler.next_f64() as f32;
+
+            if choice < specular_weight {
+                ray = Ray::create_reflection(
+                    normal.face_forward(&ray.direction),
+                    ray.direction,
+                    point,
+                    SHADOW_BIAS,
+                );
+                last_bsdf_pdf = None;
+            } else if choice < transparency {
+                let (facing, eta_i, eta_t) = if object.inside(&ray, &hit) {
+                    (-normal, index, 1.0)
+                } else {
+                    (normal, 1.0, index)
+                };
+                let kr = fresnel(ray.direction, facing, eta_i, eta_t);
+                let transmitted = if sampler.next_f64() < kr {
+                    None
+                } else {
+                    Ray::create_transmission(
+                        facing,
+                        ray.direction,
+                        point,
+                        SHADOW_BIAS,
+                        eta_i,
+                        eta_t,
+                    )
+                };
+                ray = match transmitted {
+                    Some(transmitted) => transmitted,
+                    None => Ray::create_reflection(facing, ray.direction, point, SHADOW_BIAS),
+                };
+                throughput = throughput * surface_color;
+                last_bsdf_pdf = None;
+            } else {
+                let bsdf = Bsdf::at(object, &ray, &hit);
+                let outgoing = -ray.direction;
+                radiance = radiance
+                    + throughput.clone()
+                        * self.sample_lights(scene, &point, &normal, &outgoing, &bsdf, sampler);
+
+                let sample = match bsdf.sample(&outgoing, &normal, sampler) {
+                    Some(sample) => sample,
+                    None => break,
+                };
+                throughput = throughput * sample.weight;
+                last_bsdf_pdf = Some(sample.pdf);
+                ray = Ray::create_scattered(normal, sample.direction, point, SHADOW_BIAS);
+                last_point = ray.origin;
+            }
+
+            if bounce >= self.russian_roulette_depth {
+                let survival = max_component(&throughput).min(0.95);
+                if survival <= 0.0 || sampler.next_f64() as f32 >= survival {
+                    break;
+                }
+                throughput = throughput * (1.0 / survival);
+            }
+        }
+        radiance
+    }
+}
+
+impl PathTracer {
+    fn visible(scene: &Scene, origin: &Vector3, direction: &Vector3, distance: f64) -> bool {
+        let shadow_ray = Ray {
+            origin: *origin,
+            direction: *direction,
+            differential: None,
+        };
+        scene.closest_hit(&shadow_ray).is_none_or(|shadow| {
+            shadow.distance > distance * (1.0 - LIGHT_SURFACE_EPSILON) - SHADOW_BIAS
+        })
+    }
+
+    //next event estimation: contributia directa a tuturor luminilor in punctul lovit
+    fn sample_lights(
+        &self,
+        scene: &Scene,
+        point: &Vector3,
+        normal: &Vector3,
+        outgoing: &Vector3,
+        bsdf: &Bsdf,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let mut direct = Color::black();
+        for light in scene.all_lights() {
+            let (direction, distance, incoming, light_pdf) = match *light {
+                Light::Directional(ref dlight) => (
+                    -dlight.direction.normalize(),
+                    f64::INFINITY,
+                    dlight.color.clone() * dlight.intensity,
+                    None,
+                ),
+                Light::Point(ref plight) => {
+                    let to_light = plight.point - *point;
+                    (
+                        to_light.normalize(),
+                        to_light.norm(),
+                        plight.color.clone() * light.physical_intensity(point),
+                        None,
+                    )
+                }
+                Light::Spot(ref slight) => {
+                    let to_light = slight.point - *point;
+                    (
+                        to_light.normalize(),
+                        to_light.norm(),
+                        slight.color.clone() * light.physical_intensity(point),
+                        None,
+                    )
+                }
+                Light::Area(ref alight) => {
+                    let (light_point, part) = alight.shape.sample_point(point, sampler);
+                    let to_light = light_point - *point;
+                    let pdf = area_light_pdf(alight, part, point, &light_point);
+                    if pdf <= 0.0 {
+                        continue;
+                    }
+                    (
+                        to_light.normalize(),
+                        to_light.norm(),
+                        alight.radiance() * (1.0 / pdf) as f32,
+                        Some(pdf),
+                    )
+                }
+            };
+
+            let reflected = bsdf.eval(outgoing, &direction, normal);
+            if max_component(&reflected) <= 0.0 {
+                continue;
+            }
+            let origin = Ray::create_scattered(*normal, direction, *point, SHADOW_BIAS).origin;
+            if !PathTracer::visible(scene, &origin, &direction, distance) {
+                continue;
+            }
+            let weight = match light_pdf {
+                Some(light_pdf) => {
+                    power_heuristic(light_pdf, bsdf.pdf(outgoing, &direction, normal))
+                }
+                None => 1.0,
+            };
+            let cos = normal.dot(&direction).abs();
+            direct = direct + reflected * incoming * (cos * weight) as f32;
+        }
+
+        if let Background::Environment(ref environment) = scene.background {
+            if environment.lighting {
+                if let Some((direction, incoming, pdf)) = environment.sample(sampler) {
+                    let reflected = bsdf.eval(outgoing, &direction, normal);
+                    let origin =
+                        Ray::create_scattered(*normal, direction, *point, SHADOW_BIAS).origin;
+                    if max_component(&reflected) > 0.0
+                        && PathTracer::visible(scene, &origin, &direction, f64::INFINITY)
+                    {
+                        let cos = normal.dot(&direction).abs();
+                        let weight = power_heuristic(pdf, bsdf.pdf(outgoing, &direction, normal));
+                        direct = direct + reflected * incoming * (cos * weight / pdf) as f32;
+                    }
+                }
+            }
+        }
+        direct
+    }
+}
+
+#[test]
+fn test_path_tracer_matches_direct_light_and_furnace() {
+    use crate::scenedata::{Coloration, Element, LightModel, Material, Plane, PointLight, Sphere};
+    use crate::scenemanager::SceneManager;
+    use crate::vector3::Normal3;
+
+    let material = |albedo: f32| Material {
+        coloration: Coloration::Color(white()),
+        albedo,
+        surface: SurfaceType::Diffuse,
+        specular: None,
+        emission: None,
+    };
+    let down = Ray {
+        origin: Vector3 {
+            x: 0.3,
+            y: 2.0,
+            z: 0.0,
+        },
+        direction: Vector3 {
+            x: 0.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        differential: None,
+    };
+
+    //podea + lumina punctuala, fundal negru: drumurile care sar de pe podea nu mai
+    //lovesc nimic, deci ramane doar lumina directa, identica cu modul Physical
+    let floor = Element::Plane(Plane {
+        p: Vector3::zero(),
+        normal: Normal3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        material: material(0.8),
+    });
+    let light = Light::Point(PointLight {
+        point: Vector3 {
+            x: 0.0,
+            y: 3.0,
+            z: 1.0,
+        },
+        color: white(),
+        intensity: 200.0,
+    });
+    let mut scenemgr = SceneManager::new(1, 1, 90.0, Vector3::zero(), vec![light], vec![floor]);
+    scenemgr.set_light_model(LightModel::Physical);
+    scenemgr.set_ambient(Color::black());
+    let scene = &scenemgr.scene;
+    let direct = scene.cast_ray(&down, 0, &mut Sampler::new(1));
+    let traced = PathTracer::default().radiance(scene, &down, &mut Sampler::new(1));
+    assert!((direct.red - traced.red).abs() < 1e-5);
+
+    //sfera intr-un fundal alb uniform: orice drum iese dupa o singura reflexie,
+    //deci radianta e exact albedo-ul, indiferent de sample-uri
+    let sphere = Element::Sphere(Sphere {
+        center: Vector3::zero(),
+        radius: 1.0,
+        material: material(0.5),
+    });
+    let mut scenemgr = SceneManager::new(1, 1, 90.0, Vector3::zero(), Vec::new(), vec![sphere]);
+    scenemgr.set_background(Background::Color(white()));
+    let mut sampler = Sampler::new(5);
+    for _ in 0..16 {
+        let color = PathTracer::default().radiance(&scenemgr.scene, &down, &mut sampler);
+        assert!((color.red - 0.5).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_sphere_light_samples_only_the_visible_cap() {
+    //o sfera de raza 1 la distanta 2, deasupra unei suprafete orientate spre centrul ei
+    let light = AreaLight {
+        shape: AreaShape::Sphere {
+            center: Vector3 {
+                x: 0.0,
+                y: 2.0,
+                z: 0.0,
+            },
+            radius: 1.0,
+        },
+        color: white(),
+        intensity: 1.0,
+        samples: 1,
+        visible: false,
+    };
+    let from = Vector3::zero();
+    let normal = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let mut sampler = Sampler::new(3);
+    let samples = 20000;
+    let mut irradiance = 0.0;
+    for _ in 0..samples {
+        let (light_point, part) = light.shape.sample_point(&from, &mut sampler);
+        //niciun punct de pe partea sferei care nu se vede din `from`
+        assert!(part.normal_at(&light_point).dot(&(from - light_point)) > -1e-9);
+        let direction = (light_point - from).normalize();
+        irradiance += normal.dot(&direction) / area_light_pdf(&light, part, &from, &light_point);
+    }
+    irradiance /= samples as f64;
+    //E = π L (r / d)² pentru o sfera cu radianta L = 1
+    let expected = PI * 0.25;
+    assert!((irradiance - expected).abs() < 0.01 * expected);
+}
@@ -0,0 +1,489 @@
+use crate::scenedata::{Color, TextureCoords};
+use crate::vector3::Vector3;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+//cat sunt mutate granitele tablei de sah, ca punctele aflate chiar pe ele sa cada mereu
+//in acelasi patrat
+const CHECKER_BIAS: f64 = 1e-6;
+
+//textura calculata din formula, fara imagine. punctul din spatiul ales e intai translatat
+//cu offset, apoi inmultit cu scale: la scale 2 modelul se repeta de doua ori mai des
+#[derive(Serialize, Deserialize)]
+pub struct Procedural {
+    pub pattern: Pattern,
+    #[serde(default)]
+    pub space: PatternSpace,
+    #[serde(default = "default_pattern_scale")]
+    pub scale: f64,
+    #[serde(default = "Vector3::zero")]
+    pub offset: Vector3,
+}
+
+fn default_pattern_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum PatternSpace {
+    //(u, v, 0) din texture_coords, ca o imagine
+    #[default]
+    Uv,
+    //punctul lovit in coordonatele scenei; obiectele par sculptate din material
+    World,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum Pattern {
+    //patrate de latura 1 (cuburi in World)
+    Checker {
+        even: Color,
+        odd: Color,
+    },
+    //benzi de-a lungul lui x, cu perioada 1; width = cat din perioada e even
+    Stripes {
+        even: Color,
+        odd: Color,
+        #[serde(default = "default_stripe_width")]
+        width: f64,
+    },
+    Gradient {
+        from: Color,
+        to: Color,
+        #[serde(default)]
+        shape: GradientShape,
+    },
+    //fbm mapat din [-1, 1] pe low..high
+    Noise {
+        low: Color,
+        high: Color,
+        #[serde(default)]
+        fbm: Fbm,
+    },
+    //sinus pe x deformat de turbulenta
+    Marble {
+        base: Color,
+        vein: Color,
+        #[serde(default = "default_marble_turbulence")]
+        turbulence: f64,
+        #[serde(default)]
+        fbm: Fbm,
+    },
+    //inele concentrice in jurul axei z, usor deformate de zgomot
+    Wood {
+        light: Color,
+        dark: Color,
+        #[serde(default = "default_wood_rings")]
+        rings: f64,
+        #[serde(default = "default_wood_turbulence")]
+        turbulence: f64,
+        #[serde(default)]
+        fbm: Fbm,
+    },
+}
+
+fn default_stripe_width() -> f64 {
+    0.5
+}
+
+fn default_marble_turbulence() -> f64 {
+    5.0
+}
+
+fn default_wood_rings() -> f64 {
+    8.0
+}
+
+fn default_wood_turbulence() -> f64 {
+    0.1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum GradientShape {
+    //de la x = 0 la x = 1
+    #[default]
+    Linear,
+    //de la origine pana la distanta 1
+    Radial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum NoiseKind {
+    #[default]
+    Perlin,
+    Simplex,
+}
+
+//suma de octave de zgomot, fiecare cu frecventa * lacunarity si amplitudinea * gain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fbm {
+    #[serde(default)]
+    pub noise: NoiseKind,
+    #[serde(default = "default_octaves")]
+    pub octaves: u32,
+    #[serde(default = "default_lacunarity")]
+    pub lacunarity: f64,
+    #[serde(default = "default_gain")]
+    pub gain: f64,
+}
+
+fn default_octaves() -> u32 {
+    4
+}
+
+fn default_lacunarity() -> f64 {
+    2.0
+}
+
+fn default_gain() -> f64 {
+    0.5
+}
+
+impl Default for Fbm {
+    fn default() -> Fbm {
+        Fbm {
+            noise: NoiseKind::default(),
+            octaves: default_octaves(),
+            lacunarity: default_lacunarity(),
+            gain: default_gain(),
+        }
+    }
+}
+
+impl Fbm {
+    fn octaves(&self, point: &Vector3, octave: impl Fn(f64) -> f64) -> f64 {
+        let (mut sum, mut amplitude, mut frequency, mut total) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..self.octaves.max(1) {
+            let p = *point * frequency;
+            let n = match self.noise {
+                NoiseKind::Perlin => perlin(&p),
+                NoiseKind::Simplex => simplex(&p),
+            };
+            sum += octave(n) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        //impartit la suma amplitudinilor ca rezultatul sa ramana in [-1, 1]
+        sum / total
+    }
+
+    pub fn value(&self, point: &Vector3) -> f64 {
+        self.octaves(point, |n| n)
+    }
+
+    //suma valorilor absolute, in [0, 1]; da venele ascutite ale marmurei
+    pub fn turbulence(&self, point: &Vector3) -> f64 {
+        self.octaves(point, f64::abs)
+    }
+}
+
+impl Procedural {
+    pub fn color(&self, texture_coords: &TextureCoords, point: &Vector3) -> Color {
+        let position = match self.space {
+            PatternSpace::Uv => Vector3 {
+                x: texture_coords.x as f64,
+                y: texture_coords.y as f64,
+                z: 0.0,
+            },
+            PatternSpace::World => *point,
+        };
+        self.pattern.color(&((position + self.offset) * self.scale))
+    }
+}
+
+impl Pattern {
+    pub fn color(&self, p: &Vector3) -> Color {
+        match *self {
+            Pattern::Checker { ref even, ref odd } => {
+                //un punct de pe un plan y = 0 are y = ±1e-16 dupa intersectie; fara bias
+                //floor ar alterna intre 0 si -1 si tabla ar avea pete
+                let cell = (p.x + CHECKER_BIAS).floor()
+                    + (p.y + CHECKER_BIAS).floor()
+                    + (p.z + CHECKER_BIAS).floor();
+                if cell.rem_euclid(2.0) == 0.0 {
+                    even.clone()
+                } else {
+                    odd.clone()
+                }
+            }
+            Pattern::Stripes {
+                ref even,
+                ref odd,
+                width,
+            } => {
+                if p.x - p.x.floor() < width {
+                    even.clone()
+                } else {
+                    odd.clone()
+                }
+            }
+            Pattern::Gradient {
+                ref from,
+                ref to,
+                shape,
+            } => {
+                let t = match shape {
+                    GradientShape::Linear => p.x,
+                    GradientShape::Radial => p.norm(),
+                };
+                mix(from, to, t)
+            }
+            Pattern::Noise {
+                ref low,
+                ref high,
+                ref fbm,
+            } => mix(low, high, (fbm.value(p) + 1.0) / 2.0),
+            Pattern::Marble {
+                ref base,
+                ref vein,
+                turbulence,
+                ref fbm,
+            } => {
+                let phase = 2.0 * PI * p.x + turbulence * fbm.turbulence(p);
+                mix(base, vein, (1.0 + phase.sin()) / 2.0)
+            }
+            Pattern::Wood {
+                ref light,
+                ref dark,
+                rings,
+                turbulence,
+                ref fbm,
+            } => {
+                let radius = p.x.hypot(p.y) + turbulence * fbm.value(p);
+                let ring = radius * rings;
+                mix(light, dark, ring - ring.floor())
+            }
+        }
+    }
+}
+
+//t e limitat la [0, 1]
+fn mix(from: &Color, to: &Color, t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0) as f32;
+    from.clone() * (1.0 - t) + to.clone() * t
+}
+
+//amesteca coordonatele colturilor retelei intr-un index de gradient
+fn hash(x: i64, y: i64, z: i64) -> usize {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    h as usize
+}
+
+//mijloacele muchiilor unui cub, ca in implementarea de referinta a lui Perlin
+const GRADIENTS: [[f64; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+//produsul scalar intre gradientul coltului (x, y, z) si distanta (dx, dy, dz) pana la el
+fn gradient(corner: (i64, i64, i64), dx: f64, dy: f64, dz: f64) -> f64 {
+    let g = GRADIENTS[hash(corner.0, corner.1, corner.2) % GRADIENTS.len()];
+    g[0] * dx + g[1] * dy + g[2] * dz
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+//zgomot gradient clasic, aproximativ in [-1, 1], 0 in colturile retelei
+pub fn perlin(p: &Vector3) -> f64 {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (x, y, z) = (p.x - x0, p.y - y0, p.z - z0);
+    let (i, j, k) = (x0 as i64, y0 as i64, z0 as i64);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let corner = |a: i64, b: i64, c: i64| {
+        gradient(
+            (i + a, j + b, k + c),
+            x - a as f64,
+            y - b as f64,
+            z - c as f64,
+        )
+    };
+    let front = lerp(
+        lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+        lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+        v,
+    );
+    let back = lerp(
+        lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+        lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+        v,
+    );
+    lerp(front, back, w).clamp(-1.0, 1.0)
+}
+
+//zgomot simplex (varianta lui Gustavson): 4 colturi in loc de 8, fara artefacte pe axe
+pub fn simplex(p: &Vector3) -> f64 {
+    const F3: f64 = 1.0 / 3.0;
+    const G3: f64 = 1.0 / 6.0;
+
+    //trecem in spatiul in care simplexurile sunt cuburi taiate pe diagonala
+    let s = (p.x + p.y + p.z) * F3;
+    let (i, j, k) = ((p.x + s).floor(), (p.y + s).floor(), (p.z + s).floor());
+    let t = (i + j + k) * G3;
+    let (x0, y0, z0) = (p.x - (i - t), p.y - (j - t), p.z - (k - t));
+
+    //in care dintre cele 6 tetraedre ale cubului suntem
+    let (first, second) = if x0 >= y0 {
+        if y0 >= z0 {
+            ((1, 0, 0), (1, 1, 0))
+        } else if x0 >= z0 {
+            ((1, 0, 0), (1, 0, 1))
+        } else {
+            ((0, 0, 1), (1, 0, 1))
+        }
+    } else if y0 < z0 {
+        ((0, 0, 1), (0, 1, 1))
+    } else if x0 < z0 {
+        ((0, 1, 0), (0, 1, 1))
+    } else {
+        ((0, 1, 0), (1, 1, 0))
+    };
+
+    let (i, j, k) = (i as i64, j as i64, k as i64);
+    let corners = [(0, 0, 0), first, second, (1, 1, 1)];
+    let mut sum = 0.0;
+    for (n, &(a, b, c)) in corners.iter().enumerate() {
+        let offset = n as f64 * G3;
+        let (x, y, z) = (
+            x0 - a as f64 + offset,
+            y0 - b as f64 + offset,
+            z0 - c as f64 + offset,
+        );
+        let falloff = 0.6 - x * x - y * y - z * z;
+        if falloff > 0.0 {
+            sum += falloff.powi(4) * gradient((i + a, j + b, k + c), x, y, z);
+        }
+    }
+    (32.0 * sum).clamp(-1.0, 1.0)
+}
+
+#[test]
+fn test_procedural_patterns() {
+    let black = Color::black();
+    let white = Color::gray(1.0);
+    let at = |x: f64, y: f64, z: f64| Vector3 { x, y, z };
+
+    let json = r#"{"pattern": {"Checker": {"even": {"red": 1.0, "green": 1.0, "blue": 1.0},
+        "odd": {"red": 0.0, "green": 0.0, "blue": 0.0}}}, "space": "World", "scale": 2.0}"#;
+    let checker: Procedural = serde_json::from_str(json).unwrap();
+    let uv = TextureCoords { x: 0.0, y: 0.0 };
+    //la scale 2 un patrat are latura 0.5
+    assert_eq!(checker.color(&uv, &at(0.1, 0.1, 0.1)).red, 1.0);
+    assert_eq!(checker.color(&uv, &at(0.6, 0.1, 0.1)).red, 0.0);
+    assert_eq!(checker.color(&uv, &at(-0.4, 0.1, 0.1)).red, 0.0);
+    //pe podeaua y = 0 zgomotul de rotunjire din y nu schimba patratul
+    for y in [0.0, 1e-16, -1e-16, -1e-12] {
+        assert_eq!(checker.color(&uv, &at(0.1, y, 0.1)).red, 1.0);
+        assert_eq!(checker.color(&uv, &at(0.6, y, 0.1)).red, 0.0);
+    }
+    //la fel pentru punctele gasite de raze pe un plan y = 0, din unghiuri diferite
+    {
+        use crate::render::{Intersectable, Ray, SurfacePoint};
+        use crate::scenedata::{Coloration, Element, Material, Plane, SurfaceType};
+
+        let floor = Element::Plane(Plane {
+            p: Vector3::zero(),
+            normal: at(0.0, 1.0, 0.0).into(),
+            material: Material {
+                coloration: Coloration::Procedural(serde_json::from_str(json).unwrap()),
+                albedo: 1.0,
+                surface: SurfaceType::Diffuse,
+                specular: None,
+                emission: None,
+            },
+        });
+        for i in 0..200 {
+            let target = at(0.37 * i as f64 - 30.0, 0.0, 0.11 * i as f64 - 10.0);
+            let origin = at(0.7 * i as f64, 3.0 + 0.13 * i as f64, -0.3 * i as f64);
+            let ray = Ray {
+                origin,
+                direction: (target - origin).normalize(),
+                differential: None,
+            };
+            let hit = origin + ray.direction * floor.hit(&ray).unwrap().distance;
+            let cell = (2.0 * hit.x).floor() + (2.0 * hit.z).floor();
+            let expected = if cell.rem_euclid(2.0) == 0.0 {
+                1.0
+            } else {
+                0.0
+            };
+            assert_eq!(floor.color(&SurfacePoint::from(hit)).red, expected);
+        }
+    }
+
+    let stripes = Pattern::Stripes {
+        even: white.clone(),
+        odd: black.clone(),
+        width: 0.25,
+    };
+    assert_eq!(stripes.color(&at(3.2, 0.0, 0.0)).red, 1.0);
+    assert_eq!(stripes.color(&at(3.3, 0.0, 0.0)).red, 0.0);
+
+    let gradient = Pattern::Gradient {
+        from: black.clone(),
+        to: white.clone(),
+        shape: GradientShape::Radial,
+    };
+    assert!((gradient.color(&at(0.3, 0.4, 0.0)).red - 0.5).abs() < 1e-6);
+
+    //zgomotul e 0 in colturile retelei, continuu si ramane in [-1, 1]
+    for noise in [NoiseKind::Perlin, NoiseKind::Simplex] {
+        let fbm = Fbm {
+            noise,
+            ..Fbm::default()
+        };
+        let mut seen = (f64::MAX, f64::MIN);
+        for n in 0..2000 {
+            let p = at(n as f64 * 0.173, n as f64 * 0.071, n as f64 * 0.029);
+            let value = fbm.value(&p);
+            assert!((-1.0..=1.0).contains(&value));
+            let nearby = fbm.value(&(p + at(1e-4, 0.0, 0.0)));
+            assert!((value - nearby).abs() < 1e-2);
+            seen = (seen.0.min(value), seen.1.max(value));
+        }
+        assert!(seen.0 < -0.2 && seen.1 > 0.2);
+    }
+    assert_eq!(perlin(&at(3.0, -2.0, 7.0)), 0.0);
+
+    //marmura si lemnul amesteca doar cele doua culori date
+    for pattern in [
+        Pattern::Marble {
+            base: black.clone(),
+            vein: white.clone(),
+            turbulence: default_marble_turbulence(),
+            fbm: Fbm::default(),
+        },
+        Pattern::Wood {
+            light: black.clone(),
+            dark: white.clone(),
+            rings: default_wood_rings(),
+            turbulence: default_wood_turbulence(),
+            fbm: Fbm::default(),
+        },
+    ] {
+        let color = pattern.color(&at(0.37, 0.81, 0.12));
+        assert!((0.0..=1.0).contains(&color.red) && color.red == color.green);
+    }
+}
@@ -1,42 +1,183 @@
+use crate::scenedata::AreaShape;
 use crate::scenedata::Cube;
+use crate::scenedata::Element;
+use crate::scenedata::Instance;
+use crate::scenedata::Mesh;
+use crate::scenedata::OrientedBox;
 use crate::scenedata::Plane;
+use crate::scenedata::Scene;
+use crate::scenedata::Sphere;
 use crate::scenedata::TextureCoords;
-use crate::vector3::Vector3;
-//use crate::Point;
-use crate::Scene;
-use crate::Sphere;
+use crate::scenedata::Transformed;
+use crate::scenedata::Triangle;
+use crate::transform::Transform;
+use crate::vector3::{Normal3, Point3, Vector3};
 
 pub struct Ray {
     pub origin: Vector3,
     pub direction: Vector3,
+    //doar la razele din camera; fara ele texturile nu sunt filtrate pe mipmap-uri
+    pub differential: Option<RayDifferential>,
+}
+
+//razele care ar trece prin pixelul urmator pe x si pe y
+#[derive(Clone, Copy)]
+pub struct RayDifferential {
+    pub dx_origin: Vector3,
+    pub dx_direction: Vector3,
+    pub dy_origin: Vector3,
+    pub dy_direction: Vector3,
 }
 
 impl Ray {
     pub fn create_prime(x: u32, y: u32, scene: &Scene) -> Ray {
-        //transformam coord in float-uri intre -1 si 1
-        //apoi se fixeaza pentru aspect ratio si fov
-        let fov_fix = (scene.fov.to_radians() / 2.0).tan(); //camera la dist 1 de sensor
-        let aspect_ratio = (scene.width as f64) / (scene.height as f64);
-        let x_sensor =
-            (((x as f64 + 0.5) / scene.width as f64) * 2.0 - 1.0) * aspect_ratio * fov_fix;
-        let y_sensor = (1.0 - ((y as f64 + 0.5) / scene.height as f64) * 2.0) * fov_fix; // y poz e in jos
+        Ray::create_prime_at(x as f64 + 0.5, y as f64 + 0.5, scene)
+    }
+
+    //pixel_x, pixel_y in coordonate de pixel, (x + 0.5, y + 0.5) e centrul pixelului (x, y)
+    pub fn create_prime_at(pixel_x: f64, pixel_y: f64, scene: &Scene) -> Ray {
+        scene
+            .camera
+            .create_ray(pixel_x, pixel_y, scene.width, scene.height)
+    }
+
+    //cu mai multe sample-uri pe pixel, fiecare acopera doar o parte din pixel
+    pub fn scale_differential(&mut self, scale: f64) {
+        if let Some(ref mut differential) = self.differential {
+            differential.dx_origin = self.origin + (differential.dx_origin - self.origin) * scale;
+            differential.dy_origin = self.origin + (differential.dy_origin - self.origin) * scale;
+            differential.dx_direction =
+                self.direction + (differential.dx_direction - self.direction) * scale;
+            differential.dy_direction =
+                self.direction + (differential.dy_direction - self.direction) * scale;
+        }
+    }
 
+    pub fn create_reflection(
+        normal: Vector3,
+        incident: Vector3,
+        intersection_point: Vector3,
+        bias: f64,
+    ) -> Ray {
         Ray {
-            origin: scene.ray_origin,
-            direction: Vector3 {
-                x: x_sensor,
-                y: y_sensor,
-                z: -1.0, // sensor square e la 1 unit de camera
-            }
-            .normalize(),
+            origin: intersection_point + (normal * bias),
+            direction: incident - (normal * 2.0 * incident.dot(&normal)),
+            differential: None,
+        }
+    }
+
+    //raza care pleaca din punct in directia data, deplasata pe partea normalei in care merge
+    pub fn create_scattered(
+        normal: Vector3,
+        direction: Vector3,
+        intersection_point: Vector3,
+        bias: f64,
+    ) -> Ray {
+        let side = if normal.dot(&direction) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        Ray {
+            origin: intersection_point + (side * bias),
+            direction,
+            differential: None,
+        }
+    }
+
+    //legea lui snell, normal e orientata spre partea din care vine raza
+    //None la total internal reflection
+    pub fn create_transmission(
+        normal: Vector3,
+        incident: Vector3,
+        intersection_point: Vector3,
+        bias: f64,
+        eta_i: f64,
+        eta_t: f64,
+    ) -> Option<Ray> {
+        let eta = eta_i / eta_t;
+        let cos_i = -incident.dot(&normal);
+        let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+        if k < 0.0 {
+            return None;
+        }
+
+        Some(Ray {
+            origin: intersection_point - (normal * bias),
+            direction: (incident * eta + normal * (eta * cos_i - k.sqrt())).normalize(),
+            differential: None,
+        })
+    }
+}
+
+//triunghiul lovit dintr-un mesh si coordonatele baricentrice ale punctului in el
+#[derive(Debug, Clone, Copy)]
+pub struct TriangleHit {
+    pub index: usize,
+    pub weights: [f64; 3],
+}
+
+//rezultatul intersectiei; la mesh-uri pastram si triunghiul, ca shading-ul sa nu-l mai caute
+#[derive(Debug, Clone, Copy)]
+pub struct Hit {
+    pub distance: f64,
+    pub triangle: Option<TriangleHit>,
+}
+
+//punctul de pe suprafata in care se face shading
+#[derive(Debug, Clone, Copy)]
+pub struct SurfacePoint {
+    pub point: Vector3,
+    pub triangle: Option<TriangleHit>,
+}
+
+impl From<Vector3> for SurfacePoint {
+    fn from(point: Vector3) -> SurfacePoint {
+        SurfacePoint {
+            point,
+            triangle: None,
         }
     }
 }
 
 pub trait Intersectable {
     fn intersect(&self, ray: &Ray) -> Option<f64>;
-    fn surface_normal(&self, intersection_point: &Vector3) -> Vector3;
-    fn texture_coords(&self, intersection_point: &Vector3) -> TextureCoords;
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3;
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords;
+
+    //ca intersect, dar mesh-urile completeaza si triunghiul lovit
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        self.intersect(ray).map(|distance| Hit {
+            distance,
+            triangle: None,
+        })
+    }
+
+    //un punct vecin de pe aceeasi primitiva (pentru diferentele finite ale texturii)
+    fn surface_point_near(&self, _hit: &SurfacePoint, point: &Vector3) -> SurfacePoint {
+        SurfacePoint::from(*point)
+    }
+
+    //true daca raza loveste suprafata din interior (normala e in aceeasi directie cu raza)
+    fn inside(&self, ray: &Ray, hit: &SurfacePoint) -> bool {
+        self.surface_normal(hit).dot(&ray.direction) > 0.0
+    }
+}
+
+//fractiunea de lumina reflectata (restul e refractata), ecuatiile fresnel exacte
+//normal e orientata spre partea din care vine raza
+pub fn fresnel(incident: Vector3, normal: Vector3, eta_i: f64, eta_t: f64) -> f64 {
+    let cos_i = (-incident.dot(&normal)).clamp(0.0, 1.0);
+    let sin_t = eta_i / eta_t * (1.0 - cos_i * cos_i).max(0.0).sqrt();
+    if sin_t >= 1.0 {
+        //total internal reflection
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin_t * sin_t).max(0.0).sqrt();
+    let r_s = (eta_t * cos_i - eta_i * cos_t) / (eta_t * cos_i + eta_i * cos_t);
+    let r_p = (eta_i * cos_i - eta_t * cos_t) / (eta_i * cos_i + eta_t * cos_t);
+    (r_s * r_s + r_p * r_p) / 2.0
 }
 
 impl Intersectable for Sphere {
@@ -59,20 +200,22 @@ impl Intersectable for Sphere {
             return None;
         }
 
-        let distance: f64;
-        if intersection_d1 > intersection_d2 {
-            distance = intersection_d2;
+        //daca prima radacina e in spate, raza porneste din interiorul sferei
+        let distance = if intersection_d1 < 0.0 {
+            intersection_d2
+        } else if intersection_d2 < 0.0 {
+            intersection_d1
         } else {
-            distance = intersection_d1;
-        }
+            intersection_d1.min(intersection_d2)
+        };
 
         Some(distance)
 
         //d < self.radius * self.radius //si = ?
     }
 
-    fn texture_coords(&self, intersection_point: &Vector3) -> TextureCoords {
-        let intersection_vec = *intersection_point - self.center;
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        let intersection_vec = hit.point - self.center;
         TextureCoords {
             x: (1.0 + (intersection_vec.z.atan2(intersection_vec.x) as f32) / std::f32::consts::PI)
                 / 2.0,
@@ -80,193 +223,189 @@ impl Intersectable for Sphere {
         }
     }
 
-    fn surface_normal(&self, intersection_point: &Vector3) -> Vector3 {
-        (*intersection_point - self.center).normalize()
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        (hit.point - self.center).normalize()
     }
 }
 
-impl Intersectable for Cube {
-    fn intersect(&self, ray: &Ray) -> Option<f64> {
-        //e intre centru +- 1/2 sidelength
-        let half_sidelength = self.sidelength / 2.0;
-        let unit_raydir_x = 1.0 / ray.direction.x;
-        let unit_raydir_y = 1.0 / ray.direction.y;
-        let unit_raydir_z = 1.0 / ray.direction.z;
-
-        let min_x = self.center.x - half_sidelength;
-        let max_x = self.center.x + half_sidelength;
-        let min_y = self.center.y - half_sidelength;
-        let max_y = self.center.y + half_sidelength;
-        let min_z = self.center.z - half_sidelength;
-        let max_z = self.center.z + half_sidelength;
-
-        let t_min_x = (min_x - ray.origin.x) * unit_raydir_x;
-        let t_max_x = (max_x - ray.origin.x) * unit_raydir_x;
-
-        let t_min_y = (min_y - ray.origin.y) * unit_raydir_y;
-        let t_max_y = (max_y - ray.origin.y) * unit_raydir_y;
-
-        let t_min_z = (min_z - ray.origin.z) * unit_raydir_z;
-        let t_max_z = (max_z - ray.origin.z) * unit_raydir_z;
-
-        //in caz de negative ray direction
-        let t_enter = (t_min_x.min(t_max_x))
-            .max(t_min_y.min(t_max_y))
-            .max(t_min_z.min(t_max_z));
-        let t_exit = (t_max_x.max(t_min_x))
-            .min(t_max_y.max(t_min_y))
-            .min(t_max_z.max(t_min_z));
-
-        //println!("enter {} si exit {} ", t_enter, t_exit);
-
-        //nu se intersecteaza pe camera
-        if t_enter > t_exit || t_exit < 0.0 {
-            return None;
+//geometria comuna pentru Cube si Box, in spatiul local al cutiei (centrul in origine)
+fn box_intersect(half_extents: &Vector3, ray: &Ray) -> Option<f64> {
+    let unit_raydir_x = 1.0 / ray.direction.x;
+    let unit_raydir_y = 1.0 / ray.direction.y;
+    let unit_raydir_z = 1.0 / ray.direction.z;
+
+    let t_min_x = (-half_extents.x - ray.origin.x) * unit_raydir_x;
+    let t_max_x = (half_extents.x - ray.origin.x) * unit_raydir_x;
+
+    let t_min_y = (-half_extents.y - ray.origin.y) * unit_raydir_y;
+    let t_max_y = (half_extents.y - ray.origin.y) * unit_raydir_y;
+
+    let t_min_z = (-half_extents.z - ray.origin.z) * unit_raydir_z;
+    let t_max_z = (half_extents.z - ray.origin.z) * unit_raydir_z;
+
+    //in caz de negative ray direction
+    let t_enter = (t_min_x.min(t_max_x))
+        .max(t_min_y.min(t_max_y))
+        .max(t_min_z.min(t_max_z));
+    let t_exit = (t_max_x.max(t_min_x))
+        .min(t_max_y.max(t_min_y))
+        .min(t_max_z.max(t_min_z));
+
+    //nu se intersecteaza pe camera
+    if t_enter > t_exit || t_exit < 0.0 {
+        return None;
+    }
+
+    //raza porneste din interiorul cutiei, iesim prin fata opusa
+    if t_enter < 0.0 {
+        return Some(t_exit);
+    }
+
+    Some(t_enter)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum BoxFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+//fata pe care e punctul: coordonata cea mai apropiata de marginea ei, relativ la dimensiune
+fn box_face(half_extents: &Vector3, local_point: &Vector3) -> BoxFace {
+    let rx = (local_point.x / half_extents.x).abs();
+    let ry = (local_point.y / half_extents.y).abs();
+    let rz = (local_point.z / half_extents.z).abs();
+
+    if rx >= ry && rx >= rz {
+        if local_point.x > 0.0 {
+            BoxFace::PositiveX
+        } else {
+            BoxFace::NegativeX
         }
+    } else if ry >= rz {
+        if local_point.y > 0.0 {
+            BoxFace::PositiveY
+        } else {
+            BoxFace::NegativeY
+        }
+    } else if local_point.z > 0.0 {
+        BoxFace::PositiveZ
+    } else {
+        BoxFace::NegativeZ
+    }
+}
 
-        Some(t_enter)
+fn box_normal(half_extents: &Vector3, local_point: &Vector3) -> Vector3 {
+    let (x, y, z) = match box_face(half_extents, local_point) {
+        BoxFace::PositiveX => (1.0, 0.0, 0.0),
+        BoxFace::NegativeX => (-1.0, 0.0, 0.0),
+        BoxFace::PositiveY => (0.0, 1.0, 0.0),
+        BoxFace::NegativeY => (0.0, -1.0, 0.0),
+        BoxFace::PositiveZ => (0.0, 0.0, 1.0),
+        BoxFace::NegativeZ => (0.0, 0.0, -1.0),
+    };
+    Vector3 { x, y, z }
+}
+
+//fiecare fata e vazuta din exterior cu u spre dreapta si v in jos (ca in imagine),
+//asa ca fetele opuse nu mai sunt in oglinda
+fn box_texture_coords(half_extents: &Vector3, local_point: &Vector3) -> TextureCoords {
+    let h = half_extents;
+    let p = local_point;
+    let (u, v) = match box_face(half_extents, local_point) {
+        BoxFace::PositiveX => ((h.z - p.z) / (2.0 * h.z), (h.y - p.y) / (2.0 * h.y)),
+        BoxFace::NegativeX => ((p.z + h.z) / (2.0 * h.z), (h.y - p.y) / (2.0 * h.y)),
+        BoxFace::PositiveY => ((p.x + h.x) / (2.0 * h.x), (p.z + h.z) / (2.0 * h.z)),
+        BoxFace::NegativeY => ((p.x + h.x) / (2.0 * h.x), (h.z - p.z) / (2.0 * h.z)),
+        BoxFace::PositiveZ => ((p.x + h.x) / (2.0 * h.x), (h.y - p.y) / (2.0 * h.y)),
+        BoxFace::NegativeZ => ((h.x - p.x) / (2.0 * h.x), (h.y - p.y) / (2.0 * h.y)),
+    };
+    TextureCoords {
+        x: u as f32,
+        y: v as f32,
     }
+}
 
-    //cum am impl si pentru sfera
-    fn texture_coords(&self, intersection_point: &Vector3) -> TextureCoords {
+impl Cube {
+    pub(crate) fn half_extents(&self) -> Vector3 {
         let half_sidelength = self.sidelength / 2.0;
-        let intersection_vec = *intersection_point - self.center;
-        let abs_x = intersection_vec.x.abs();
-        let abs_y = intersection_vec.y.abs();
-        let abs_z = intersection_vec.z.abs();
-
-        let face = if abs_x > abs_y && abs_x > abs_z {
-            if intersection_vec.x > 0.0 {
-                0
-            } else {
-                1
-            }
-        } else if abs_y > abs_z {
-            if intersection_vec.y > 0.0 {
-                2
-            } else {
-                3
-            }
-        } else if intersection_vec.z > 0.0 {
-            4
-        } else {
-            5
+        Vector3 {
+            x: half_sidelength,
+            y: half_sidelength,
+            z: half_sidelength,
+        }
+    }
+}
+
+impl Intersectable for Cube {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        let local_ray = Ray {
+            origin: ray.origin - self.center,
+            direction: ray.direction,
+            differential: None,
         };
+        box_intersect(&self.half_extents(), &local_ray)
+    }
 
-        let mut texture_coords = TextureCoords { x: 0.0, y: 0.0 };
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        box_texture_coords(&self.half_extents(), &(hit.point - self.center))
+    }
 
-        match face {
-            0 => {
-                texture_coords.x =
-                    ((intersection_vec.z + half_sidelength) / self.sidelength) as f32;
-                texture_coords.y =
-                    ((intersection_vec.y + half_sidelength) / self.sidelength) as f32;
-            }
-            1 => {
-                texture_coords.x =
-                    ((intersection_vec.z + half_sidelength) / self.sidelength) as f32;
-                texture_coords.y =
-                    ((intersection_vec.y + half_sidelength) / self.sidelength) as f32;
-            }
-            2 => {
-                texture_coords.x =
-                    ((intersection_vec.x + half_sidelength) / self.sidelength) as f32;
-                texture_coords.y =
-                    ((intersection_vec.z + half_sidelength) / self.sidelength) as f32;
-            }
-            3 => {
-                texture_coords.x =
-                    ((intersection_vec.x + half_sidelength) / self.sidelength) as f32;
-                texture_coords.y =
-                    ((intersection_vec.z + half_sidelength) / self.sidelength) as f32;
-            }
-            4 => {
-                texture_coords.x =
-                    ((intersection_vec.x + half_sidelength) / self.sidelength) as f32;
-                texture_coords.y =
-                    ((intersection_vec.y + half_sidelength) / self.sidelength) as f32;
-            }
-            5 => {
-                texture_coords.x =
-                    ((intersection_vec.x + half_sidelength) / self.sidelength) as f32;
-                texture_coords.y =
-                    ((intersection_vec.y + half_sidelength) / self.sidelength) as f32;
-            }
-            _default => {}
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        box_normal(&self.half_extents(), &(hit.point - self.center))
+    }
+}
+
+impl OrientedBox {
+    fn to_local(&self, point: &Vector3) -> Vector3 {
+        let local = *point - self.center;
+        match self.rotation {
+            Some(ref rotation) => local.rotate_euler_inverse(rotation),
+            None => local,
         }
-        texture_coords
     }
+}
 
-    fn surface_normal(&self, intersection_point: &Vector3) -> Vector3 {
-        let half_sidelength = self.sidelength / 2.0;
-        let min_x = self.center.x - half_sidelength;
-        let max_x = self.center.x + half_sidelength;
-        let min_y = self.center.y - half_sidelength;
-        let max_y = self.center.y + half_sidelength;
-        let min_z = self.center.z - half_sidelength;
-        let max_z = self.center.z + half_sidelength;
-
-        let d_min_x = (intersection_point.x - min_x).abs();
-        let d_max_x = (intersection_point.x - max_x).abs();
-        let d_min_y = (intersection_point.y - min_y).abs();
-        let d_max_y = (intersection_point.y - max_y).abs();
-        let d_min_z = (intersection_point.z - min_z).abs();
-        let d_max_z = (intersection_point.z - max_z).abs();
-
-        let min_d = d_min_x
-            .min(d_max_x)
-            .min(d_min_y.min(d_max_y))
-            .min(d_min_z.min(d_max_z));
-
-        if min_d == d_min_x {
-            Vector3 {
-                x: -1.0,
-                y: 0.0,
-                z: 0.0,
-            }
-        } else if min_d == d_max_x {
-            Vector3 {
-                x: 1.0,
-                y: 0.0,
-                z: 0.0,
-            }
-        } else if min_d == d_min_y {
-            Vector3 {
-                x: 0.0,
-                y: -1.0,
-                z: 0.0,
-            }
-        } else if min_d == d_max_y {
-            Vector3 {
-                x: 0.0,
-                y: 1.0,
-                z: 0.0,
-            }
-        } else if min_d == d_min_z {
-            Vector3 {
-                x: 0.0,
-                y: 0.0,
-                z: -1.0,
-            }
-        } else {
-            Vector3 {
-                x: 0.0,
-                y: 0.0,
-                z: 1.0,
-            }
+impl Intersectable for OrientedBox {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        //rotatia nu schimba lungimea directiei, deci distanta e aceeasi si in spatiul lumii
+        let local_ray = Ray {
+            origin: self.to_local(&ray.origin),
+            direction: match self.rotation {
+                Some(ref rotation) => ray.direction.rotate_euler_inverse(rotation),
+                None => ray.direction,
+            },
+            differential: None,
+        };
+        box_intersect(&self.half_extents, &local_ray)
+    }
+
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        box_texture_coords(&self.half_extents, &self.to_local(&hit.point))
+    }
+
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        let local_normal = box_normal(&self.half_extents, &self.to_local(&hit.point));
+        match self.rotation {
+            Some(ref rotation) => local_normal.rotate_euler(rotation),
+            None => local_normal,
         }
     }
 }
 
 impl Intersectable for Plane {
     fn intersect(&self, ray: &Ray) -> Option<f64> {
-        let normal = &(self.normal);
+        let normal = &Vector3::from(self.normal);
         let denominator = normal.dot(&ray.direction);
 
         //abs pentru ambele fete ale planului
         if denominator.abs() > 1e-6 {
             let v = self.p - ray.origin;
-            let d = v.dot(&normal) / denominator;
+            let d = v.dot(normal) / denominator;
             if d > 0.0 {
                 return Some(d);
             }
@@ -274,31 +413,572 @@ impl Intersectable for Plane {
         None
     }
 
-    fn texture_coords(&self, intersection_point: &Vector3) -> TextureCoords {
-        let mut x_axis = self.normal.cross(&Vector3 {
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        let normal = Vector3::from(self.normal);
+        let mut x_axis = normal.cross(&Vector3 {
             x: 0.0,
             y: 0.0,
             z: 1.0,
         });
 
         if x_axis.norm() < 0.01 {
-            x_axis = self.normal.cross(&Vector3 {
+            x_axis = normal.cross(&Vector3 {
                 x: 0.0,
                 y: 1.0,
                 z: 0.0,
             });
         }
 
-        let y_axis = self.normal.cross(&x_axis);
+        let y_axis = normal.cross(&x_axis);
 
-        let intersection_vec = *intersection_point - self.p;
+        let intersection_vec = hit.point - self.p;
         TextureCoords {
             x: intersection_vec.dot(&x_axis) as f32,
             y: intersection_vec.dot(&y_axis) as f32,
         }
     }
 
-    fn surface_normal(&self, _intersection_point: &Vector3) -> Vector3 {
-        -self.normal
+    //normala declarata; planul e vizibil din ambele parti, shading-ul o intoarce spre raza
+    fn surface_normal(&self, _hit: &SurfacePoint) -> Vector3 {
+        Vector3::from(self.normal)
+    }
+}
+
+//test watertight (woop, benthin, wald): muchiile comune a doua triunghiuri nu lasa gauri
+//intoarce distanta si coordonatele baricentrice pentru v0, v1, v2
+pub fn intersect_triangle(vertices: &[Vector3; 3], ray: &Ray) -> Option<(f64, [f64; 3])> {
+    let dir = ray.direction;
+    let abs_dir = [dir.x.abs(), dir.y.abs(), dir.z.abs()];
+    let kz = if abs_dir[0] > abs_dir[1] && abs_dir[0] > abs_dir[2] {
+        0
+    } else if abs_dir[1] > abs_dir[2] {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
     }
+
+    //forfecare ca raza sa devina axa z
+    let shear_x = dir[kx] / dir[kz];
+    let shear_y = dir[ky] / dir[kz];
+    let shear_z = 1.0 / dir[kz];
+
+    let a = vertices[0] - ray.origin;
+    let b = vertices[1] - ray.origin;
+    let c = vertices[2] - ray.origin;
+
+    let ax = a[kx] - shear_x * a[kz];
+    let ay = a[ky] - shear_y * a[kz];
+    let bx = b[kx] - shear_x * b[kz];
+    let by = b[ky] - shear_y * b[kz];
+    let cx = c[kx] - shear_x * c[kz];
+    let cy = c[ky] - shear_y * c[kz];
+
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let az = shear_z * a[kz];
+    let bz = shear_z * b[kz];
+    let cz = shear_z * c[kz];
+    let t = (u * az + v * bz + w * cz) / det;
+    if t <= 0.0 {
+        return None;
+    }
+
+    Some((t, [u / det, v / det, w / det]))
+}
+
+//coordonatele baricentrice ale unui punct aflat (aproximativ) in planul triunghiului
+fn barycentric(vertices: &[Vector3; 3], point: &Vector3) -> [f64; 3] {
+    let e1 = vertices[1] - vertices[0];
+    let e2 = vertices[2] - vertices[0];
+    let p = *point - vertices[0];
+    let d00 = e1.dot(&e1);
+    let d01 = e1.dot(&e2);
+    let d11 = e2.dot(&e2);
+    let d20 = p.dot(&e1);
+    let d21 = p.dot(&e2);
+    let denominator = d00 * d11 - d01 * d01;
+    if denominator.abs() < f64::EPSILON {
+        return [1.0, 0.0, 0.0];
+    }
+    let v = (d11 * d20 - d01 * d21) / denominator;
+    let w = (d00 * d21 - d01 * d20) / denominator;
+    [1.0 - v - w, v, w]
+}
+
+fn face_normal(vertices: &[Vector3; 3]) -> Vector3 {
+    (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .normalize()
+}
+
+fn interpolate_normal(
+    vertices: &[Vector3; 3],
+    normals: Option<[Vector3; 3]>,
+    weights: &[f64; 3],
+) -> Vector3 {
+    match normals {
+        Some(n) => (n[0] * weights[0] + n[1] * weights[1] + n[2] * weights[2]).normalize(),
+        None => face_normal(vertices),
+    }
+}
+
+//fara uv-uri folosim chiar coordonatele baricentrice
+fn interpolate_uv(uvs: Option<[[f32; 2]; 3]>, weights: &[f64; 3]) -> TextureCoords {
+    match uvs {
+        Some(uv) => {
+            let w = weights.map(|w| w as f32);
+            TextureCoords {
+                x: uv[0][0] * w[0] + uv[1][0] * w[1] + uv[2][0] * w[2],
+                y: uv[0][1] * w[0] + uv[1][1] * w[1] + uv[2][1] * w[2],
+            }
+        }
+        None => TextureCoords {
+            x: weights[1] as f32,
+            y: weights[2] as f32,
+        },
+    }
+}
+
+impl Intersectable for Triangle {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        intersect_triangle(&self.vertices, ray).map(|(t, _)| t)
+    }
+
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        interpolate_uv(self.uvs, &barycentric(&self.vertices, &hit.point))
+    }
+
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        let weights = barycentric(&self.vertices, &hit.point);
+        interpolate_normal(&self.vertices, self.normals, &weights)
+    }
+}
+
+impl Mesh {
+    fn vertex_normals(&self, index: usize) -> Option<[Vector3; 3]> {
+        if self.data.normals.is_empty() {
+            return None;
+        }
+        let [a, b, c] = self.data.indices[index];
+        Some([
+            self.data.normals[a],
+            self.data.normals[b],
+            self.data.normals[c],
+        ])
+    }
+
+    fn vertex_uvs(&self, index: usize) -> Option<[[f32; 2]; 3]> {
+        if self.data.uvs.is_empty() {
+            return None;
+        }
+        let [a, b, c] = self.data.indices[index];
+        Some([self.data.uvs[a], self.data.uvs[b], self.data.uvs[c]])
+    }
+}
+
+//punctele de pe un mesh vin din Mesh::hit, care completeaza mereu triunghiul
+fn mesh_triangle(hit: &SurfacePoint) -> &TriangleHit {
+    hit.triangle
+        .as_ref()
+        .expect("mesh surface point without the triangle it was hit on")
+}
+
+impl Intersectable for Mesh {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        self.hit(ray).map(|hit| hit.distance)
+    }
+
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        self.bvh
+            .closest_hit(ray, |index| {
+                intersect_triangle(&self.triangle_vertices(index), ray).map(
+                    |(distance, weights)| Hit {
+                        distance,
+                        triangle: Some(TriangleHit { index, weights }),
+                    },
+                )
+            })
+            .map(|(_, hit)| hit)
+    }
+
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        let triangle = mesh_triangle(hit);
+        interpolate_uv(self.vertex_uvs(triangle.index), &triangle.weights)
+    }
+
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        let triangle = mesh_triangle(hit);
+        interpolate_normal(
+            &self.triangle_vertices(triangle.index),
+            self.vertex_normals(triangle.index),
+            &triangle.weights,
+        )
+    }
+
+    //acelasi triunghi, coordonatele baricentrice prelungite in planul lui
+    fn surface_point_near(&self, hit: &SurfacePoint, point: &Vector3) -> SurfacePoint {
+        let index = mesh_triangle(hit).index;
+        SurfacePoint {
+            point: *point,
+            triangle: Some(TriangleHit {
+                index,
+                weights: barycentric(&self.triangle_vertices(index), point),
+            }),
+        }
+    }
+}
+
+fn plane_distance(point: &Vector3, normal: &Vector3, ray: &Ray) -> Option<f64> {
+    let denominator = normal.dot(&ray.direction);
+    if denominator.abs() <= 1e-9 {
+        return None;
+    }
+    let d = (*point - ray.origin).dot(normal) / denominator;
+    if d > 0.0 {
+        Some(d)
+    } else {
+        None
+    }
+}
+
+//formele luminilor de suprafata sunt vizibile din ambele parti
+impl AreaShape {
+    pub fn intersect(&self, ray: &Ray) -> Option<f64> {
+        match *self {
+            AreaShape::Sphere { center, radius } => {
+                let v = center - ray.origin;
+                let cateta = v.dot(&ray.direction);
+                let d = v.dot(&v) - cateta * cateta;
+                if d > radius * radius {
+                    return None;
+                }
+                let cut_d = (radius * radius - d).sqrt();
+                [cateta - cut_d, cateta + cut_d]
+                    .into_iter()
+                    .find(|&distance| distance > 0.0)
+            }
+            AreaShape::Rect { center, u, v } => {
+                let d = plane_distance(&center, &u.cross(&v), ray)?;
+                let local = ray.origin + ray.direction * d - center;
+                if (local.dot(&u) / u.dot(&u)).abs() <= 1.0
+                    && (local.dot(&v) / v.dot(&v)).abs() <= 1.0
+                {
+                    Some(d)
+                } else {
+                    None
+                }
+            }
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let d = plane_distance(&center, &normal, ray)?;
+                if (ray.origin + ray.direction * d - center).norm() <= radius {
+                    Some(d)
+                } else {
+                    None
+                }
+            }
+            AreaShape::Triangle { ref vertices } => {
+                intersect_triangle(vertices, ray).map(|(d, _)| d)
+            }
+            AreaShape::Compound { .. } => self.intersect_part(ray).map(|(d, _)| d),
+        }
+    }
+
+    //distanta si bucata lovita; la formele simple bucata e forma insasi
+    pub fn intersect_part(&self, ray: &Ray) -> Option<(f64, &AreaShape)> {
+        match *self {
+            AreaShape::Compound {
+                ref parts, ref bvh, ..
+            } => bvh
+                .closest_hit(ray, |i| {
+                    parts[i].intersect(ray).map(|distance| Hit {
+                        distance,
+                        triangle: None,
+                    })
+                })
+                .map(|(i, hit)| (hit.distance, &parts[i])),
+            _ => self.intersect(ray).map(|d| (d, self)),
+        }
+    }
+}
+
+//distanta din spatiul obiectului se imparte la cat s-a scalat directia razei
+fn transformed_hit(transform: &Transform, element: &Element, ray: &Ray) -> Option<Hit> {
+    let (local_ray, scale) = transform.ray_to_local(ray);
+    element.hit(&local_ray).map(|hit| Hit {
+        distance: hit.distance / scale,
+        triangle: hit.triangle,
+    })
+}
+
+//coordonatele baricentrice nu se schimba printr-o transformare afina
+fn local_surface_point(transform: &Transform, hit: &SurfacePoint) -> SurfacePoint {
+    SurfacePoint {
+        point: Vector3::from(transform.point_to_local(&Point3::from(hit.point))),
+        triangle: hit.triangle,
+    }
+}
+
+fn transformed_normal(transform: &Transform, element: &Element, hit: &SurfacePoint) -> Vector3 {
+    let local = element.surface_normal(&local_surface_point(transform, hit));
+    Vector3::from(transform.normal_to_world(&Normal3::from(local)))
+}
+
+fn transformed_point_near(
+    transform: &Transform,
+    element: &Element,
+    hit: &SurfacePoint,
+    point: &Vector3,
+) -> SurfacePoint {
+    let local = element.surface_point_near(
+        &local_surface_point(transform, hit),
+        &Vector3::from(transform.point_to_local(&Point3::from(*point))),
+    );
+    SurfacePoint {
+        point: *point,
+        triangle: local.triangle,
+    }
+}
+
+impl Intersectable for Transformed {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        self.hit(ray).map(|hit| hit.distance)
+    }
+
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        transformed_hit(&self.transform, &self.element, ray)
+    }
+
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        self.element
+            .texture_coords(&local_surface_point(&self.transform, hit))
+    }
+
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        transformed_normal(&self.transform, &self.element, hit)
+    }
+
+    fn surface_point_near(&self, hit: &SurfacePoint, point: &Vector3) -> SurfacePoint {
+        transformed_point_near(&self.transform, &self.element, hit, point)
+    }
+}
+
+//o instanta nerezolvata nu e lovita de nicio raza
+impl Intersectable for Instance {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        self.hit(ray).map(|hit| hit.distance)
+    }
+
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        self.target
+            .as_ref()
+            .and_then(|target| transformed_hit(&self.transform, target, ray))
+    }
+
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        match self.target {
+            Some(ref target) => target.texture_coords(&local_surface_point(&self.transform, hit)),
+            None => TextureCoords { x: 0.0, y: 0.0 },
+        }
+    }
+
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        match self.target {
+            Some(ref target) => transformed_normal(&self.transform, target, hit),
+            None => Vector3::zero(),
+        }
+    }
+
+    fn surface_point_near(&self, hit: &SurfacePoint, point: &Vector3) -> SurfacePoint {
+        match self.target {
+            Some(ref target) => transformed_point_near(&self.transform, target, hit, point),
+            None => SurfacePoint::from(*point),
+        }
+    }
+}
+
+#[test]
+fn test_sphere_intersect_from_inside() {
+    use crate::scenedata::{Color, Coloration, Material, SurfaceType};
+
+    let sphere = Sphere {
+        center: Vector3::zero(),
+        radius: 2.0,
+        material: Material {
+            coloration: Coloration::Color(Color::black()),
+            albedo: 1.0,
+            surface: SurfaceType::Refractive {
+                index: 1.5,
+                transparency: 1.0,
+            },
+            specular: None,
+            emission: None,
+        },
+    };
+    let ray = Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    };
+
+    let distance = sphere.intersect(&ray).unwrap();
+    assert!((distance - 2.0).abs() < 1e-9);
+    let hit = SurfacePoint::from(ray.origin + ray.direction * distance);
+    assert!(sphere.inside(&ray, &hit));
+
+    //din sticla spre aer la unghi mare avem total internal reflection
+    let grazing = Vector3 {
+        x: 0.9,
+        y: 0.0,
+        z: -(1.0f64 - 0.81).sqrt(),
+    };
+    let normal = Vector3 {
+        x: 0.0,
+        y: 0.0,
+        z: 1.0,
+    };
+    assert_eq!(fresnel(grazing, normal, 1.5, 1.0), 1.0);
+    assert!(Ray::create_transmission(normal, grazing, Vector3::zero(), 1e-7, 1.5, 1.0).is_none());
+}
+
+#[test]
+fn test_mesh_quad_is_watertight_and_interpolates_uvs() {
+    use crate::scenedata::{Color, Coloration, Element, Material, MeshData, SurfaceType};
+
+    let material = || Material {
+        coloration: Coloration::Color(Color::black()),
+        albedo: 1.0,
+        surface: SurfaceType::Diffuse,
+        specular: None,
+        emission: None,
+    };
+    let corner = |x: f64, y: f64| Vector3 { x, y, z: -2.0 };
+    let data = MeshData {
+        positions: vec![
+            corner(-1.0, -1.0),
+            corner(1.0, -1.0),
+            corner(1.0, 1.0),
+            corner(-1.0, 1.0),
+        ],
+        normals: Vec::new(),
+        uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+        indices: vec![[0, 1, 2], [0, 2, 3]],
+    };
+    let mesh = Mesh::new(data, material()).unwrap();
+
+    //raza exact pe diagonala comuna a celor doua triunghiuri
+    let diagonal = Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.25,
+            y: 0.25,
+            z: -2.0,
+        }
+        .normalize(),
+        differential: None,
+    };
+    let hit = mesh.hit(&diagonal).expect("ray on shared edge missed");
+    let hit = SurfacePoint {
+        point: diagonal.origin + diagonal.direction * hit.distance,
+        triangle: hit.triangle,
+    };
+    let uv = mesh.texture_coords(&hit);
+    assert!((uv.x - 0.625).abs() < 1e-5 && (uv.y - 0.625).abs() < 1e-5);
+    assert!((mesh.surface_normal(&hit).z.abs() - 1.0).abs() < 1e-9);
+
+    let json = serde_json::to_string(&Element::Mesh(mesh)).unwrap();
+    let loaded: Element = serde_json::from_str(&json).unwrap();
+    assert!(loaded.intersect(&diagonal).is_some());
+
+    let broken = r#"{"Mesh":{"data":{"positions":[],"indices":[[0,1,2]]},"material":{"coloration":{"Color":{"red":0.0,"green":0.0,"blue":0.0}},"albedo":1.0}}}"#;
+    assert!(serde_json::from_str::<Element>(broken).is_err());
+}
+
+#[test]
+fn test_box_faces_and_rotation() {
+    use crate::scenedata::{Color, Coloration, Material, SurfaceType};
+
+    let material = || Material {
+        coloration: Coloration::Color(Color::black()),
+        albedo: 1.0,
+        surface: SurfaceType::Diffuse,
+        specular: None,
+        emission: None,
+    };
+    let cube = Cube {
+        center: Vector3::zero(),
+        sidelength: 2.0,
+        material: material(),
+    };
+
+    //un punct din dreapta fetei din fata si acelasi punct pe fata din spate,
+    //vazut din spate e in stanga, deci u-urile sunt complementare
+    let front = cube.texture_coords(&SurfacePoint::from(Vector3 {
+        x: 0.5,
+        y: 0.5,
+        z: 1.0,
+    }));
+    let back = cube.texture_coords(&SurfacePoint::from(Vector3 {
+        x: 0.5,
+        y: 0.5,
+        z: -1.0,
+    }));
+    assert!((front.x - 0.75).abs() < 1e-6 && (back.x - 0.25).abs() < 1e-6);
+    assert!((front.y - 0.25).abs() < 1e-6 && (back.y - 0.25).abs() < 1e-6);
+
+    //cutie lunga pe x, rotita 90 de grade in jurul lui y devine lunga pe z
+    let slab = OrientedBox {
+        center: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -10.0,
+        },
+        half_extents: Vector3 {
+            x: 4.0,
+            y: 1.0,
+            z: 0.5,
+        },
+        rotation: Some(Vector3 {
+            x: 0.0,
+            y: 90.0,
+            z: 0.0,
+        }),
+        material: material(),
+    };
+    let ray = Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    };
+    let distance = slab.intersect(&ray).unwrap();
+    assert!((distance - 6.0).abs() < 1e-9);
+    let normal = slab.surface_normal(&SurfacePoint::from(ray.direction * distance));
+    assert!((normal.z - 1.0).abs() < 1e-9);
 }
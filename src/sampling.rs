@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+
+//generator pseudo-aleator mic (pcg32), ca sa nu depindem de un crate extern
+//fiecare pixel primeste propriul generator derivat din seed, deci randarea e reproductibila
+//si nu depinde de ordinea in care sunt randati pixelii
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+    increment: u64,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Sampler {
+        let mut sampler = Sampler {
+            state: 0,
+            increment: (seed << 1) | 1,
+        };
+        sampler.next_u32();
+        sampler.state = sampler.state.wrapping_add(splitmix64(seed));
+        sampler.next_u32();
+        sampler
+    }
+
+    pub fn for_pixel(seed: u64, x: u32, y: u32) -> Sampler {
+        Sampler::new(splitmix64(seed ^ splitmix64(((x as u64) << 32) | y as u64)))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    //numar uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        self.next_u32() as f64 / (u32::MAX as f64 + 1.0)
+    }
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub enum ReconstructionFilter {
+    #[default]
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+}
+
+impl ReconstructionFilter {
+    //cat de departe de centrul pixelului (in pixeli) se intind sample-urile
+    pub fn radius(&self) -> f64 {
+        match *self {
+            ReconstructionFilter::Box => 0.5,
+            ReconstructionFilter::Tent => 1.0,
+            ReconstructionFilter::Gaussian => 1.5,
+            ReconstructionFilter::Mitchell => 2.0,
+        }
+    }
+
+    //ponderea unui sample aflat la (dx, dy) pixeli de centrul pixelului
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        match *self {
+            ReconstructionFilter::Box => 1.0,
+            ReconstructionFilter::Tent => (1.0 - dx.abs()).max(0.0) * (1.0 - dy.abs()).max(0.0),
+            ReconstructionFilter::Gaussian => {
+                let r = self.radius();
+                gaussian_1d(dx, r) * gaussian_1d(dy, r)
+            }
+            ReconstructionFilter::Mitchell => mitchell_1d(dx) * mitchell_1d(dy),
+        }
+    }
+}
+
+fn gaussian_1d(x: f64, radius: f64) -> f64 {
+    const ALPHA: f64 = 2.0;
+    ((-ALPHA * x * x).exp() - (-ALPHA * radius * radius).exp()).max(0.0)
+}
+
+//mitchell-netravali cu B = C = 1/3, definit pe [-2, 2]
+fn mitchell_1d(x: f64) -> f64 {
+    const B: f64 = 1.0 / 3.0;
+    const C: f64 = 1.0 / 3.0;
+    let x = x.abs();
+    if x < 1.0 {
+        ((12.0 - 9.0 * B - 6.0 * C) * x * x * x
+            + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+            + (6.0 - 2.0 * B))
+            / 6.0
+    } else if x < 2.0 {
+        ((-B - 6.0 * C) * x * x * x
+            + (6.0 * B + 30.0 * C) * x * x
+            + (-12.0 * B - 48.0 * C) * x
+            + (8.0 * B + 24.0 * C))
+            / 6.0
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sampling {
+    #[serde(default = "default_samples_per_pixel")]
+    pub samples_per_pixel: u32,
+    #[serde(default)]
+    pub filter: ReconstructionFilter,
+    //false = sample-urile stau fix in centrul fiecarei celule din grila
+    #[serde(default = "default_jitter")]
+    pub jitter: bool,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+fn default_samples_per_pixel() -> u32 {
+    1
+}
+
+fn default_jitter() -> bool {
+    true
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling {
+            samples_per_pixel: default_samples_per_pixel(),
+            filter: ReconstructionFilter::default(),
+            jitter: default_jitter(),
+            seed: 0,
+        }
+    }
+}
+
+impl Sampling {
+    //offset-urile (dx, dy) fata de centrul pixelului, stratificate pe o grila
+    //care acopera suportul filtrului. fiecare sample are o celula de aceeasi arie:
+    //cand count nu e patrat perfect, unele randuri au o coloana in plus si sunt mai inalte
+    pub fn pixel_offsets(&self, sampler: &mut Sampler) -> Vec<(f64, f64)> {
+        let count = self.samples_per_pixel.max(1) as usize;
+        let rows = ((count as f64).sqrt() as usize).max(1);
+        let radius = self.filter.radius();
+
+        let mut offsets = Vec::with_capacity(count);
+        let mut row_start = 0;
+        for row in 0..rows {
+            let columns = count / rows + usize::from(row < count % rows);
+            for column in 0..columns {
+                let (jx, jy) = if self.jitter {
+                    (sampler.next_f64(), sampler.next_f64())
+                } else {
+                    (0.5, 0.5)
+                };
+                let u = (column as f64 + jx) / columns as f64;
+                let v = (row_start as f64 + jy * columns as f64) / count as f64;
+                offsets.push(((u * 2.0 - 1.0) * radius, (v * 2.0 - 1.0) * radius));
+            }
+            row_start += columns;
+        }
+        offsets
+    }
+}
+
+//distributie discreta pe n intervale egale din [0, 1), proportionala cu valorile date
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    values: Vec<f64>,
+    cdf: Vec<f64>,
+    total: f64,
+}
+
+impl Distribution1D {
+    pub fn new(values: Vec<f64>) -> Distribution1D {
+        let count = values.len();
+        let mut cdf = Vec::with_capacity(count + 1);
+        cdf.push(0.0);
+        for value in values.iter() {
+            cdf.push(cdf.last().unwrap() + value.max(0.0) / count as f64);
+        }
+        let total = cdf[count];
+        //toate valorile 0: esantionam uniform
+        if total <= 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f64 / count as f64;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= total;
+            }
+        }
+        Distribution1D { values, cdf, total }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    //media valorilor, adica integrala functiei pe [0, 1)
+    pub fn integral(&self) -> f64 {
+        self.total
+    }
+
+    //intoarce pozitia continua in [0, 1), indicele intervalului si densitatea
+    pub fn sample(&self, u: f64) -> (f64, usize, f64) {
+        let index = self.cdf.partition_point(|&c| c <= u).clamp(1, self.len()) - 1;
+        let width = self.cdf[index + 1] - self.cdf[index];
+        let offset = if width > 0.0 {
+            (u - self.cdf[index]) / width
+        } else {
+            0.0
+        };
+        (
+            (index as f64 + offset) / self.len() as f64,
+            index,
+            self.pdf(index),
+        )
+    }
+
+    pub fn pdf(&self, index: usize) -> f64 {
+        if self.total <= 0.0 {
+            1.0
+        } else {
+            self.values[index].max(0.0) / self.total
+        }
+    }
+}
+
+//distributie pe [0, 1)^2 data de un tabel de valori pe randuri (ca o imagine)
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(values: &[f64], width: usize, height: usize) -> Distribution2D {
+        let rows: Vec<Distribution1D> = values
+            .chunks(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(rows.iter().map(|row| row.integral()).collect());
+        Distribution2D { rows, marginal }
+    }
+
+    //(u, v) in [0, 1)^2 si densitatea in raport cu aria din [0, 1)^2
+    pub fn sample(&self, u1: f64, u2: f64) -> ((f64, f64), f64) {
+        let (v, row, pdf_row) = self.marginal.sample(u2);
+        let (u, _, pdf_column) = self.rows[row].sample(u1);
+        ((u, v), pdf_row * pdf_column)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.len() as f64) as usize).min(self.marginal.len() - 1);
+        let columns = &self.rows[row];
+        let column = ((u * columns.len() as f64) as usize).min(columns.len() - 1);
+        self.marginal.pdf(row) * columns.pdf(column)
+    }
+}
+
+#[test]
+fn test_sampler_is_reproducible() {
+    let mut a = Sampler::for_pixel(7, 10, 20);
+    let mut b = Sampler::for_pixel(7, 10, 20);
+    let mut c = Sampler::for_pixel(8, 10, 20);
+    let first: Vec<f64> = (0..16).map(|_| a.next_f64()).collect();
+    let second: Vec<f64> = (0..16).map(|_| b.next_f64()).collect();
+    let other: Vec<f64> = (0..16).map(|_| c.next_f64()).collect();
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+    assert!(first.iter().all(|v| (0.0..1.0).contains(v)));
+
+    let sampling = Sampling {
+        samples_per_pixel: 9,
+        filter: ReconstructionFilter::Tent,
+        jitter: true,
+        seed: 7,
+    };
+    let offsets = sampling.pixel_offsets(&mut Sampler::for_pixel(7, 0, 0));
+    assert_eq!(offsets.len(), 9);
+    assert!(offsets
+        .iter()
+        .all(|(dx, dy)| dx.abs() <= 1.0 && dy.abs() <= 1.0));
+
+    //fara jitter sample-urile sunt in centrele celulelor; daca celulele acopera tot
+    //suportul cu arii egale, media lor e chiar centrul pixelului
+    for samples_per_pixel in 1..=12 {
+        let grid = Sampling {
+            samples_per_pixel,
+            jitter: false,
+            ..sampling.clone()
+        };
+        let offsets = grid.pixel_offsets(&mut Sampler::new(0));
+        assert_eq!(offsets.len(), samples_per_pixel as usize);
+        let (sum_x, sum_y) = offsets
+            .iter()
+            .fold((0.0, 0.0), |(x, y), (dx, dy)| (x + dx, y + dy));
+        assert!(sum_x.abs() < 1e-9 && sum_y.abs() < 1e-9);
+    }
+}
@@ -1,20 +1,159 @@
-use crate::render::{Intersectable, Ray};
-use crate::vector3::Vector3;
-use image::{open, DynamicImage, GenericImageView, Rgba};
-use serde::{de, Deserialize, Deserializer, Serialize};
+use crate::background::{Background, EnvironmentMap};
+use crate::bsdf::Bsdf;
+use crate::bvh::{Aabb, Bvh};
+use crate::camera::Camera;
+use crate::error::SceneError;
+use crate::integrator::{Integrator, RenderMode};
+use crate::procedural::Procedural;
+use crate::render::{fresnel, Hit, Intersectable, Ray, RayDifferential, SurfacePoint, TriangleHit};
+use crate::sampling::{Distribution1D, Sampler, Sampling};
+use crate::texture::{
+    MipChain, MipmapMode, TextureFilter, TextureFootprint, UvTransform, WrapMode,
+};
+use crate::transform::Transform;
+use crate::vector3::{Normal3, Point3, Vector3};
+use image::{open, DynamicImage, Rgba};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs;
 use std::ops::{Add, Mul};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub const AMBIENT_LIGHT_INTENSITY: f32 = 0.075;
+pub const DEFAULT_MAX_RECURSION_DEPTH: u32 = 5;
+pub(crate) const SHADOW_BIAS: f64 = 1e-7;
+//punctele alese pe obiectele emisive sunt chiar pe geometrie: umbra de la ele insele
+//(la distanta luminii, pana la erori de rotunjire) nu conteaza
+pub(crate) const LIGHT_SURFACE_EPSILON: f64 = 1e-6;
+//cate puncte se aleg pe un obiect emisiv
+const EMITTER_SAMPLES: u32 = 16;
+
+//pentru instantele al caror prototip nu a fost inca gasit
+static UNRESOLVED_MATERIAL: Material = Material {
+    coloration: Coloration::Color(Color::black()),
+    albedo: 0.0,
+    surface: SurfaceType::Diffuse,
+    specular: None,
+    emission: None,
+};
 
 #[derive(Serialize, Deserialize)]
+#[serde(try_from = "SceneDescription")]
 pub struct Scene {
-    pub ray_origin: Vector3,
+    pub camera: Camera,
     pub width: u32,
     pub height: u32,
-    pub fov: f64,
     pub lights: Vec<Light>,
-    pub objects: Vec<Element>,
+    //privat: orice schimbare trece prin set_objects/add_object/remove_object, care refac bvh-ul
+    objects: Vec<Element>,
+    //cate raze pot fi intr-un lant (raza din camera plus reflexiile/refractiile ei),
+    //ca doua oglinzi fata in fata sa nu mearga la infinit
+    pub max_recursion_depth: u32,
+    pub sampling: Sampling,
+    #[serde(default)]
+    pub light_model: LightModel,
+    //folosit doar de LightModel::Physical; in modul legacy fiecare lumina isi adauga ambientul
+    #[serde(default = "default_ambient")]
+    pub ambient: Color,
+    //ce vad razele care nu lovesc nimic
+    #[serde(default)]
+    pub background: Background,
+    //integratorul ales din json
+    #[serde(default)]
+    pub render_mode: RenderMode,
+    //integrator setat din cod; daca exista, are prioritate fata de render_mode
+    #[serde(skip)]
+    pub custom_integrator: Option<Arc<dyn Integrator>>,
+    //soarele cerului din background, derivat cu update_sun; nu se salveaza in json
+    #[serde(skip)]
+    pub sun: Option<Light>,
+    //lumini de suprafata generate din obiectele cu material emisiv, refacute de build_bvh
+    #[serde(skip)]
+    pub(crate) emitters: Vec<Light>,
+    //obiecte refolosite de Element::Instance, cautate dupa nume
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prototypes: Vec<Prototype>,
+    //reconstruit cu build_bvh dupa ce se schimba objects; None dupa deserializare,
+    //pana cand load_assets citeste mesh-urile si il construieste
+    #[serde(skip)]
+    bvh: Option<Bvh>,
+}
+
+//forma din json; scenele vechi au doar ray_origin si fov in loc de camera
+#[derive(Deserialize)]
+struct SceneDescription {
+    #[serde(default)]
+    camera: Option<Camera>,
+    #[serde(default)]
+    ray_origin: Option<Vector3>,
+    #[serde(default)]
+    fov: Option<f64>,
+    width: u32,
+    height: u32,
+    lights: Vec<Light>,
+    objects: Vec<Element>,
+    #[serde(default = "default_max_recursion_depth")]
+    max_recursion_depth: u32,
+    #[serde(default)]
+    sampling: Sampling,
+    #[serde(default)]
+    light_model: LightModel,
+    #[serde(default = "default_ambient")]
+    ambient: Color,
+    #[serde(default)]
+    background: Background,
+    #[serde(default)]
+    render_mode: RenderMode,
+    #[serde(default)]
+    prototypes: Vec<Prototype>,
+}
+
+fn default_max_recursion_depth() -> u32 {
+    DEFAULT_MAX_RECURSION_DEPTH
+}
+
+pub fn default_ambient() -> Color {
+    Color {
+        red: AMBIENT_LIGHT_INTENSITY,
+        green: AMBIENT_LIGHT_INTENSITY,
+        blue: AMBIENT_LIGHT_INTENSITY,
+    }
+}
+
+impl TryFrom<SceneDescription> for Scene {
+    type Error = String;
+
+    fn try_from(description: SceneDescription) -> Result<Self, Self::Error> {
+        let camera = match (description.camera, description.ray_origin, description.fov) {
+            (Some(camera), _, _) => camera,
+            (None, Some(ray_origin), Some(fov)) => Camera::new(ray_origin, fov),
+            (None, _, _) => {
+                return Err("scene needs a \"camera\" or \"ray_origin\" and \"fov\"".to_string())
+            }
+        };
+
+        let mut scene = Scene {
+            max_recursion_depth: description.max_recursion_depth,
+            sampling: description.sampling,
+            light_model: description.light_model,
+            ambient: description.ambient,
+            background: description.background,
+            render_mode: description.render_mode,
+            prototypes: description.prototypes,
+            ..Scene::empty(
+                description.width,
+                description.height,
+                camera,
+                description.lights,
+                description.objects,
+            )
+        };
+        //texturile, mesh-urile din fisiere si instantele sunt citite de load_assets,
+        //care construieste si bvh-ul
+        scene.update_sun();
+        Ok(scene)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -31,28 +170,218 @@ pub struct PointLight {
     pub intensity: f32,
 }
 
+//lumina punctuala restransa la un con; unghiurile sunt masurate fata de direction, in grade.
+//in interiorul lui inner_angle lumineaza complet, intre inner si outer scade lin spre 0
+#[derive(Serialize, Deserialize)]
+pub struct SpotLight {
+    pub point: Vector3,
+    pub direction: Vector3,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+//forma unei lumini de suprafata; u si v la Rect sunt jumatatile laturilor
+#[derive(Serialize, Deserialize)]
+pub enum AreaShape {
+    Sphere {
+        center: Vector3,
+        radius: f64,
+    },
+    Rect {
+        center: Vector3,
+        u: Vector3,
+        v: Vector3,
+    },
+    Disk {
+        center: Vector3,
+        normal: Vector3,
+        radius: f64,
+    },
+    Triangle {
+        vertices: [Vector3; 3],
+    },
+    //obiect emisiv din mai multe bucati plate (fetele unei cutii, triunghiurile unui mesh);
+    //bucata e aleasa proportional cu aria ei. Nu apare in json, e construit de Element::emitter
+    #[serde(skip)]
+    Compound {
+        parts: Vec<AreaShape>,
+        areas: Distribution1D,
+        bvh: Bvh,
+    },
+}
+
+//lumina cu suprafata: umbrele sunt calculate din `samples` puncte de pe forma
+#[derive(Serialize, Deserialize)]
+pub struct AreaLight {
+    pub shape: AreaShape,
+    pub color: Color,
+    pub intensity: f32,
+    #[serde(default = "default_shadow_samples")]
+    pub samples: u32,
+    //daca e true, razele care o lovesc direct o vad ca pe un obiect emisiv
+    #[serde(default)]
+    pub visible: bool,
+}
+
+fn default_shadow_samples() -> u32 {
+    16
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub enum LightModel {
+    //modelul initial: 1/(4π·d) si ambient adaugat de fiecare lumina, si in umbra
+    #[default]
+    Legacy,
+    //1/d² si ambientul scenei adaugat o singura data
+    Physical,
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum Light {
     Directional(DirectionalLight),
     Point(PointLight),
+    Spot(SpotLight),
+    Area(AreaLight),
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Material {
     pub coloration: Coloration,
     pub albedo: f32,
+    #[serde(default)]
+    pub surface: SurfaceType,
+    //highlight blinn-phong peste partea difuza; lipsa = fara highlight, ca inainte
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub specular: Option<BlinnPhong>,
+    //obiectul emite lumina: se vede direct si lumineaza scena ca o lumina de suprafata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emission: Option<Emission>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Emission {
+    pub color: Color,
+    #[serde(default = "default_emission_strength")]
+    pub strength: f32,
+}
+
+fn default_emission_strength() -> f32 {
+    1.0
+}
+
+impl Emission {
+    //radianta emisa, la fel in toate directiile si pe ambele fete
+    pub fn radiance(&self) -> Color {
+        self.color.clone() * self.strength
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlinnPhong {
+    #[serde(default = "default_specular_color")]
+    pub color: Color,
+    //exponentul (n·h)^shininess: mare = highlight mic si intens
+    #[serde(default = "default_shininess")]
+    pub shininess: f32,
+    #[serde(default = "default_specular_weight")]
+    pub weight: f32,
+}
+
+fn default_specular_color() -> Color {
+    Color::gray(1.0)
+}
+
+fn default_shininess() -> f32 {
+    32.0
+}
+
+fn default_specular_weight() -> f32 {
+    1.0
+}
+
+impl BlinnPhong {
+    //normalizat cu (n + 8) / 8pi, ca luminozitatea totala sa nu depinda de shininess
+    pub fn eval(&self, view: &Vector3, incoming: &Vector3, surface_normal: &Vector3) -> Color {
+        if surface_normal.dot(view) <= 0.0 {
+            return Color::black();
+        }
+        let half = (*view + *incoming).normalize();
+        let cos = surface_normal.dot(&half).max(0.0) as f32;
+        let normalization = (self.shininess + 8.0) / (8.0 * std::f32::consts::PI);
+        self.color.clone() * (self.weight * normalization * cos.powf(self.shininess))
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub enum SurfaceType {
+    #[default]
+    Diffuse,
+    //0 = doar difuz, 1 = oglinda perfecta
+    Reflective {
+        reflectivity: f32,
+    },
+    //sticla, apa; index = indicele de refractie (1.5 sticla, 1.33 apa)
+    Refractive {
+        index: f32,
+        transparency: f32,
+    },
+    //metal cu microfatete GGX; culoarea e reflectanta la incidenta normala
+    Conductor {
+        #[serde(default)]
+        roughness: f32,
+    },
+    //sticla cu microfatete GGX; roughness 0 = sticla lustruita
+    Dielectric {
+        index: f32,
+        #[serde(default)]
+        roughness: f32,
+    },
+    //metallic-roughness, ca in materialele exportate din Blender/glTF
+    Principled {
+        #[serde(default)]
+        metallic: f32,
+        #[serde(default = "default_roughness")]
+        roughness: f32,
+        //0.5 = reflexie de 4% la incidenta normala, ca majoritatea dielectricilor
+        #[serde(default = "default_specular")]
+        specular: f32,
+    },
+}
+
+fn default_roughness() -> f32 {
+    0.5
+}
+
+fn default_specular() -> f32 {
+    0.5
+}
+
+//mipmap-urile sunt impartite intre copii, clone e ieftin
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Texture {
     pub path: PathBuf,
 
-    #[serde(skip_serializing, skip_deserializing, default = "default_texture")]
-    pub texture: DynamicImage,
+    //imaginea e citita in Scene::load_assets; dupa aceea ramane doar mips
+    #[serde(skip)]
+    loaded: bool,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub filter: TextureFilter,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub mipmap: MipmapMode,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub wrap: WrapMode,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub transform: UvTransform,
+    //construit o singura data, cand se citeste imaginea
+    #[serde(skip)]
+    pub(crate) mips: Arc<MipChain>,
 }
 
-fn default_texture() -> DynamicImage {
-    DynamicImage::new_rgb8(0, 0)
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
 }
 
 pub struct TextureCoords {
@@ -63,7 +392,8 @@ pub struct TextureCoords {
 #[derive(Serialize, Deserialize)]
 pub enum Coloration {
     Color(Color),
-    Texture(#[serde(deserialize_with = "load_texture")] Texture),
+    Texture(#[serde(deserialize_with = "deserialize_texture")] Texture),
+    Procedural(Procedural),
 }
 
 #[derive(Serialize, Deserialize)]
@@ -80,21 +410,104 @@ pub struct Cube {
     pub material: Material,
 }
 
+//cutie cu dimensiuni diferite pe fiecare axa, optional rotita (grade, x apoi y apoi z)
+#[derive(Serialize, Deserialize)]
+pub struct OrientedBox {
+    pub center: Vector3,
+    pub half_extents: Vector3,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<Vector3>,
+    pub material: Material,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Plane {
     pub p: Vector3,
-    pub normal: Vector3,
+    pub normal: Normal3,
+    pub material: Material,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Triangle {
+    pub vertices: [Vector3; 3],
+    //normale per varf pentru smooth shading, altfel se foloseste normala fetei
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normals: Option<[Vector3; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uvs: Option<[[f32; 2]; 3]>,
+    pub material: Material,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct MeshData {
+    pub positions: Vec<Vector3>,
+    //daca exista, cate una pentru fiecare pozitie
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normals: Vec<Vector3>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+//in json mesh-ul e fie inline ("data"), fie o referinta la un fisier json cu MeshData ("path")
+#[derive(Deserialize)]
+#[serde(try_from = "MeshDescription")]
+pub struct Mesh {
+    //relativ la directorul fisierului json al scenei
+    pub path: Option<PathBuf>,
+    pub data: MeshData,
     pub material: Material,
+    pub(crate) bvh: Bvh,
+    //un mesh gol, fara triunghiuri, e tot incarcat
+    loaded: bool,
+}
+
+#[derive(Deserialize)]
+struct MeshDescription {
+    #[serde(default)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    data: Option<MeshData>,
+    material: Material,
+}
+
+//orice element, mutat/rotit/scalat de o matrice; raza e adusa in spatiul obiectului
+#[derive(Serialize, Deserialize)]
+pub struct Transformed {
+    pub transform: Transform,
+    pub element: Box<Element>,
+}
+
+//copie a unui prototip din scene.prototypes; elementul nu e duplicat, doar referit
+#[derive(Serialize, Deserialize)]
+pub struct Instance {
+    pub prototype: String,
+    #[serde(default = "Transform::identity")]
+    pub transform: Transform,
+    //completat de Scene::load_assets dupa nume
+    #[serde(skip)]
+    pub target: Option<Arc<Element>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Prototype {
+    pub name: String,
+    pub element: Arc<Element>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub enum Element {
     Sphere(Sphere),
     Cube(Cube),
+    Box(OrientedBox),
     Plane(Plane),
+    Triangle(Triangle),
+    Mesh(Mesh),
+    Transformed(Transformed),
+    Instance(Instance),
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Color {
     pub red: f32,
     pub green: f32,
@@ -155,6 +568,22 @@ impl Color {
         }
     }
 
+    pub const fn black() -> Color {
+        Color {
+            red: 0.0,
+            green: 0.0,
+            blue: 0.0,
+        }
+    }
+
+    pub const fn gray(value: f32) -> Color {
+        Color {
+            red: value,
+            green: value,
+            blue: value,
+        }
+    }
+
     pub fn clamp(&self) -> Color {
         Color {
             red: self.red.clamp(0.0, 1.0),
@@ -164,205 +593,2659 @@ impl Color {
     }
 }
 
-fn wrap(val: f32, max: u32) -> u32 {
-    let signed_max = max as i32;
-    let wrapped_coord = (val * max as f32) as i32 % signed_max;
-    if wrapped_coord < 0 {
-        (wrapped_coord + signed_max) as u32
-    } else {
-        wrapped_coord as u32
-    }
-}
-
-fn load_texture<'de, D>(deserializer: D) -> Result<Texture, D::Error>
+//textura propriu-zisa e citita abia in Scene::load_assets, ca lipsa ei sa fie o eroare separata
+fn deserialize_texture<'de, D>(deserializer: D) -> Result<Texture, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let path: PathBuf = PathBuf::deserialize(deserializer)?;
+    //save_to_json scrie {"path": ...}, dar acceptam si doar path-ul
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TexturePath {
+        Path(PathBuf),
+        Struct {
+            path: PathBuf,
+            #[serde(default)]
+            filter: TextureFilter,
+            #[serde(default)]
+            mipmap: MipmapMode,
+            #[serde(default)]
+            wrap: WrapMode,
+            #[serde(default)]
+            transform: UvTransform,
+        },
+    }
+
+    Ok(match TexturePath::deserialize(deserializer)? {
+        TexturePath::Path(path) => Texture::unloaded(path),
+        TexturePath::Struct {
+            path,
+            filter,
+            mipmap,
+            wrap,
+            transform,
+        } => Texture {
+            filter,
+            mipmap,
+            wrap,
+            transform,
+            ..Texture::unloaded(path)
+        },
+    })
+}
+
+impl Texture {
+    pub fn unloaded(path: PathBuf) -> Texture {
+        Texture {
+            path,
+            loaded: false,
+            filter: TextureFilter::default(),
+            mipmap: MipmapMode::default(),
+            wrap: WrapMode::default(),
+            transform: UvTransform::default(),
+            mips: Arc::default(),
+        }
+    }
+
+    pub fn from_image(path: PathBuf, texture: DynamicImage) -> Texture {
+        let mut result = Texture::unloaded(path);
+        result.set_image(texture);
+        result
+    }
+
+    pub fn load(path: PathBuf) -> Result<Texture, SceneError> {
+        let mut texture = Texture::unloaded(path);
+        texture.load_image(Path::new(""))?;
+        Ok(texture)
+    }
+
+    //citeste imaginea de la path, cautat in base_dir, pastrand setarile de filtrare
+    pub fn load_image(&mut self, base_dir: &Path) -> Result<(), SceneError> {
+        let path = base_dir.join(&self.path);
+        match open(&path) {
+            Ok(texture) => {
+                self.set_image(texture);
+                Ok(())
+            }
+            Err(source) => Err(SceneError::MissingTexture { path, source }),
+        }
+    }
+
+    //imaginea nu e pastrata: se esantioneaza doar din mipmap-uri
+    fn set_image(&mut self, texture: DynamicImage) {
+        self.mips = Arc::new(MipChain::new(&texture));
+        self.loaded = true;
+    }
 
-    match open(&path) {
-        Ok(img) => Ok(Texture { path, texture: img }),
-        Err(err) => Err(de::Error::custom(format!(
-            "Unable to open texture file {:?}: {}",
-            path, err
-        ))),
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
     }
 }
 
 impl Coloration {
-    pub fn color(&self, texture_coords: &TextureCoords) -> Color {
+    //point = punctul lovit, in coordonatele scenei, pentru modelele procedurale in World
+    pub fn color(&self, texture_coords: &TextureCoords, point: &Vector3) -> Color {
+        self.filtered_color(texture_coords, point, None)
+    }
+
+    //footprint-ul alege nivelul de mipmap; fara el se citeste rezolutia initiala
+    pub fn filtered_color(
+        &self,
+        texture_coords: &TextureCoords,
+        point: &Vector3,
+        footprint: Option<&TextureFootprint>,
+    ) -> Color {
         match *self {
             Coloration::Color(ref c) => c.clone(),
+            Coloration::Procedural(ref procedural) => procedural.color(texture_coords, point),
+            Coloration::Texture(ref tex) if !tex.is_loaded() => Color::black(),
             Coloration::Texture(ref tex) => {
-                let x = wrap(texture_coords.x, tex.texture.width());
-                let y = wrap(texture_coords.y, tex.texture.height());
-                Color::from_rgb(tex.texture.get_pixel(x, y))
-            } /* Color::from_rgb(tex.get_pixel(
-                  wrap(texture_coords.x, tex.width()),
-                  wrap(texture_coords.y, tex.height()),
-              )), */
+                let footprint = footprint.map(|f| tex.transform.apply_footprint(f));
+                tex.mips.sample(
+                    &tex.transform.apply(texture_coords),
+                    footprint.as_ref(),
+                    tex.filter,
+                    tex.mipmap,
+                    &tex.wrap,
+                )
+            }
         }
     }
 }
 
+//bounding box usor marit ca triunghiurile paralele cu o axa sa nu aiba grosime 0
+fn triangle_bounds(vertices: &[Vector3; 3]) -> Aabb {
+    let padding = Vector3 {
+        x: 1e-9,
+        y: 1e-9,
+        z: 1e-9,
+    };
+    let bounds = Aabb::empty()
+        .grow(&vertices[0])
+        .grow(&vertices[1])
+        .grow(&vertices[2]);
+    Aabb::new(bounds.min - padding, bounds.max + padding)
+}
+
+impl Mesh {
+    pub fn new(data: MeshData, material: Material) -> Result<Mesh, SceneError> {
+        let mut mesh = Mesh {
+            path: None,
+            data: MeshData::default(),
+            material,
+            bvh: Bvh::default(),
+            loaded: false,
+        };
+        mesh.set_data(data)?;
+        Ok(mesh)
+    }
+
+    pub fn from_file(path: PathBuf, material: Material) -> Result<Mesh, SceneError> {
+        let mut mesh = Mesh {
+            path: Some(path),
+            data: MeshData::default(),
+            material,
+            bvh: Bvh::default(),
+            loaded: false,
+        };
+        mesh.load(Path::new(""))?;
+        Ok(mesh)
+    }
+
+    //mesh-urile referite prin path sunt citite abia aici; path e cautat in base_dir
+    pub fn load(&mut self, base_dir: &Path) -> Result<(), SceneError> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        let path = base_dir.join(path);
+        let json_data = fs::read_to_string(&path).map_err(|source| SceneError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let data: MeshData = serde_json::from_str(&json_data)
+            .map_err(|err| SceneError::from_json(path.clone(), err))?;
+        self.set_data(data)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    fn set_data(&mut self, data: MeshData) -> Result<(), SceneError> {
+        Mesh::validate(&data)?;
+        let bounds: Vec<Option<Aabb>> = data
+            .indices
+            .iter()
+            .map(|&[a, b, c]| {
+                Some(triangle_bounds(&[
+                    data.positions[a],
+                    data.positions[b],
+                    data.positions[c],
+                ]))
+            })
+            .collect();
+        self.bvh = Bvh::build(&bounds);
+        self.data = data;
+        self.loaded = true;
+        Ok(())
+    }
+
+    fn validate(data: &MeshData) -> Result<(), SceneError> {
+        if !data.normals.is_empty() && data.normals.len() != data.positions.len() {
+            return Err(SceneError::Validation(format!(
+                "mesh has {} normals for {} positions",
+                data.normals.len(),
+                data.positions.len()
+            )));
+        }
+        if !data.uvs.is_empty() && data.uvs.len() != data.positions.len() {
+            return Err(SceneError::Validation(format!(
+                "mesh has {} uvs for {} positions",
+                data.uvs.len(),
+                data.positions.len()
+            )));
+        }
+        for (i, triangle) in data.indices.iter().enumerate() {
+            if let Some(index) = triangle.iter().find(|&&v| v >= data.positions.len()) {
+                return Err(SceneError::Validation(format!(
+                    "mesh triangle {} references vertex {} but there are only {} positions",
+                    i,
+                    index,
+                    data.positions.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn triangle_vertices(&self, index: usize) -> [Vector3; 3] {
+        let [a, b, c] = self.data.indices[index];
+        [
+            self.data.positions[a],
+            self.data.positions[b],
+            self.data.positions[c],
+        ]
+    }
+}
+
+impl TryFrom<MeshDescription> for Mesh {
+    type Error = String;
+
+    fn try_from(description: MeshDescription) -> Result<Self, Self::Error> {
+        match (description.path, description.data) {
+            //se citeste in Scene::load_assets
+            (Some(path), None) => Ok(Mesh {
+                path: Some(path),
+                data: MeshData::default(),
+                material: description.material,
+                bvh: Bvh::default(),
+                loaded: false,
+            }),
+            (None, Some(data)) => {
+                Mesh::new(data, description.material).map_err(|err| err.to_string())
+            }
+            (Some(_), Some(_)) => {
+                Err("Mesh must have either \"path\" or \"data\", not both".to_string())
+            }
+            (None, None) => Err("Mesh needs a \"path\" or inline \"data\"".to_string()),
+        }
+    }
+}
+
+impl Serialize for Mesh {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("Mesh", 2)?;
+        match self.path {
+            Some(ref path) => state.serialize_field("path", path)?,
+            None => state.serialize_field("data", &self.data)?,
+        }
+        state.serialize_field("material", &self.material)?;
+        state.end()
+    }
+}
+
 pub struct Intersection<'a> {
     pub distance: f64,
     pub object: &'a Element,
+    //triunghiul lovit, daca obiectul e (sau contine) un mesh
+    pub triangle: Option<TriangleHit>,
 }
 
 impl<'a> Intersection<'a> {
-    pub fn new<'b>(distance: f64, object: &'b Element) -> Intersection<'b> {
-        Intersection { distance, object }
+    pub fn new<'b>(hit: Hit, object: &'b Element) -> Intersection<'b> {
+        Intersection {
+            distance: hit.distance,
+            object,
+            triangle: hit.triangle,
+        }
     }
-}
 
-impl Light {
-    pub fn intensity(&self, intersection_point: &Vector3) -> f32 {
-        match *self {
-            Light::Directional(ref dlight) => dlight.intensity + AMBIENT_LIGHT_INTENSITY,
-            Light::Point(ref plight) => {
-                plight.intensity
-                    / (4.0
-                        * ::std::f32::consts::PI
-                        * ((plight.point - *intersection_point).norm() as f32))
-                    + AMBIENT_LIGHT_INTENSITY
-            }
+    pub fn surface_point(&self, ray: &Ray) -> SurfacePoint {
+        SurfacePoint {
+            point: ray.origin + ray.direction * self.distance,
+            triangle: self.triangle,
         }
     }
+}
 
-    pub fn color(&self) -> &Color {
-        match *self {
-            Light::Directional(ref dlight) => &dlight.color,
-            Light::Point(ref plight) => &plight.color,
+impl SpotLight {
+    //1 in interiorul conului, 0 in afara, smoothstep pe cosinus intre ele
+    pub fn cone_falloff(&self, intersection_point: &Vector3) -> f32 {
+        let to_point = (*intersection_point - self.point).normalize();
+        let cos_angle = to_point.dot(&self.direction.normalize());
+        let cos_inner = self.inner_angle.to_radians().cos();
+        let cos_outer = self.outer_angle.to_radians().cos();
+        if cos_angle >= cos_inner {
+            return 1.0;
         }
+        if cos_angle <= cos_outer {
+            return 0.0;
+        }
+        let t = ((cos_angle - cos_outer) / (cos_inner - cos_outer)) as f32;
+        t * t * (3.0 - 2.0 * t)
     }
+}
 
-    pub fn dir_to_light(&self, intersection_point: &Vector3) -> Vector3 {
+fn point_light_falloff(intensity: f32, point: &Vector3, intersection_point: &Vector3) -> f32 {
+    intensity / (4.0 * ::std::f32::consts::PI * ((*point - *intersection_point).norm() as f32))
+}
+
+fn inverse_square_falloff(intensity: f32, point: &Vector3, intersection_point: &Vector3) -> f32 {
+    let distance = (*point - *intersection_point).norm() as f32;
+    intensity / (4.0 * ::std::f32::consts::PI * distance * distance)
+}
+
+impl LightModel {
+    //cat primeste un punct aflat in umbra unei lumini
+    pub fn shadowed_intensity(&self) -> f32 {
         match *self {
-            Light::Directional(ref dlight) => -dlight.direction.normalize(),
-            Light::Point(ref plight) => (plight.point - *intersection_point).normalize(),
+            LightModel::Legacy => AMBIENT_LIGHT_INTENSITY,
+            LightModel::Physical => 0.0,
         }
     }
+}
 
-    pub fn lit(
+impl AreaLight {
+    //contributia unui singur punct ales pe suprafata luminii; `part` e bucata formei pe care
+    //se afla, vezi AreaShape::sample_point
+    pub fn intensity_at(
         &self,
-        traced_shadow_checker: &Option<Intersection>,
+        light_point: &Vector3,
+        part: &AreaShape,
         intersection_point: &Vector3,
-    ) -> bool {
-        match *self {
-            Light::Directional(ref _dlight) => traced_shadow_checker.is_none(),
-            Light::Point(ref plight) => {
-                traced_shadow_checker.is_none()
-                    || traced_shadow_checker.as_ref().unwrap().distance
-                        > (plight.point - *intersection_point).norm()
+        model: LightModel,
+    ) -> f32 {
+        match model {
+            LightModel::Legacy => {
+                point_light_falloff(self.intensity, light_point, intersection_point)
+                    + AMBIENT_LIGHT_INTENSITY
+            }
+            LightModel::Physical => {
+                //rect si disk emit din ambele fete, deci puterea e impartita pe doua emisfere
+                let dir_from_light = (*intersection_point - *light_point).normalize();
+                let emitter_cos = match *part {
+                    AreaShape::Sphere { .. } => 1.0,
+                    _ => 2.0 * part.normal_at(light_point).dot(&dir_from_light).abs(),
+                };
+                inverse_square_falloff(self.intensity, light_point, intersection_point)
+                    * emitter_cos as f32
             }
         }
     }
 }
 
-impl Element {
-    /*
-    pub fn color(&self) -> &Color {
-        match *self {
-            Element::Sphere(ref s) => &(s.material.color),
-            Element::Cube(ref c) => &(c.material.color),
-            Element::Plane(ref p) => &(p.material.color),
-        }
+impl AreaLight {
+    //radianta unui emitator lambertian care imprastie toata puterea `intensity`;
+    //rect si disk emit pe ambele fete
+    pub fn radiance(&self) -> Color {
+        self.color.clone()
+            * (self.intensity as f64 / (std::f64::consts::PI * self.shape.emitting_area())) as f32
     }
+}
 
-
-    pub fn albedo(&self) -> f32 {
-        1.0
-    }
-    */
-    pub fn color(&self, texture_coords: &TextureCoords) -> Color {
+impl AreaShape {
+    //aria unei singure fete
+    pub fn area(&self) -> f64 {
         match *self {
-            Element::Sphere(ref s) => s.material.coloration.color(texture_coords),
-            Element::Cube(ref c) => c.material.coloration.color(texture_coords),
-            Element::Plane(ref p) => p.material.coloration.color(texture_coords),
+            AreaShape::Sphere { radius, .. } => 4.0 * std::f64::consts::PI * radius * radius,
+            AreaShape::Rect { u, v, .. } => 4.0 * u.cross(&v).norm(),
+            AreaShape::Disk { radius, .. } => std::f64::consts::PI * radius * radius,
+            AreaShape::Triangle { vertices } => {
+                0.5 * (vertices[1] - vertices[0])
+                    .cross(&(vertices[2] - vertices[0]))
+                    .norm()
+            }
+            AreaShape::Compound { ref parts, .. } => parts.iter().map(|part| part.area()).sum(),
         }
     }
 
-    pub fn albedo(&self) -> f32 {
+    //toata suprafata care emite, numarand ambele fete acolo unde e cazul
+    pub fn emitting_area(&self) -> f64 {
         match *self {
-            Element::Sphere(ref s) => s.material.albedo,
-            Element::Cube(ref c) => c.material.albedo,
-            Element::Plane(ref p) => p.material.albedo,
+            AreaShape::Sphere { .. } => self.area(),
+            AreaShape::Rect { .. } | AreaShape::Disk { .. } | AreaShape::Triangle { .. } => {
+                2.0 * self.area()
+            }
+            AreaShape::Compound { ref parts, .. } => {
+                parts.iter().map(|part| part.emitting_area()).sum()
+            }
         }
     }
-}
 
-impl Intersectable for Element {
-    fn intersect(&self, ray: &Ray) -> Option<f64> {
+    pub fn normal_at(&self, point: &Vector3) -> Vector3 {
         match *self {
-            Element::Sphere(ref s) => s.intersect(ray),
-            Element::Cube(ref c) => c.intersect(ray),
-            Element::Plane(ref p) => p.intersect(ray),
+            AreaShape::Sphere { center, .. } => (*point - center).normalize(),
+            AreaShape::Rect { u, v, .. } => u.cross(&v).normalize(),
+            AreaShape::Disk { normal, .. } => normal.normalize(),
+            AreaShape::Triangle { vertices } => (vertices[1] - vertices[0])
+                .cross(&(vertices[2] - vertices[0]))
+                .normalize(),
+            //bucata cu planul cel mai apropiat de punct
+            AreaShape::Compound { ref parts, .. } => {
+                let distance =
+                    |part: &AreaShape| (*point - part.center()).dot(&part.normal_at(point)).abs();
+                parts
+                    .iter()
+                    .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                    .map_or(Vector3::zero(), |part| part.normal_at(point))
+            }
         }
     }
 
-    fn texture_coords(&self, intersection_point: &Vector3) -> TextureCoords {
+    pub fn center(&self) -> Vector3 {
         match *self {
-            Element::Sphere(ref s) => s.texture_coords(intersection_point),
-            Element::Cube(ref c) => c.texture_coords(intersection_point),
-            Element::Plane(ref p) => p.texture_coords(intersection_point),
+            AreaShape::Sphere { center, .. }
+            | AreaShape::Rect { center, .. }
+            | AreaShape::Disk { center, .. } => center,
+            AreaShape::Triangle { vertices } => (vertices[0] + vertices[1] + vertices[2]) / 3.0,
+            //centrul de greutate al bucatilor
+            AreaShape::Compound { ref parts, .. } => {
+                parts.iter().fold(Vector3::zero(), |sum, part| {
+                    sum + part.center() * part.area()
+                }) / self.area()
+            }
         }
     }
 
-    fn surface_normal(&self, intersection_point: &Vector3) -> Vector3 {
-        match *self {
-            Element::Sphere(ref s) => s.surface_normal(intersection_point),
-            Element::Cube(ref c) => c.surface_normal(intersection_point),
-            Element::Plane(ref p) => p.surface_normal(intersection_point),
+    //punct uniform pe suprafata; la sfera o directie uniforma in conul sub care e vazuta
+    //din `toward` si punctul de pe partea vizibila din acea directie.
+    //intoarce si bucata formei pe care e punctul (forma insasi, daca nu e Compound)
+    pub fn sample_point(&self, toward: &Vector3, sampler: &mut Sampler) -> (Vector3, &AreaShape) {
+        if let AreaShape::Compound {
+            ref parts,
+            ref areas,
+            ..
+        } = *self
+        {
+            let (_, index, _) = areas.sample(sampler.next_f64());
+            return parts[index].sample_point(toward, sampler);
         }
+        let (a, b) = (sampler.next_f64(), sampler.next_f64());
+        let point = match *self {
+            AreaShape::Sphere { center, radius } => {
+                let to_center = center - *toward;
+                let distance = to_center.norm();
+                let Some(cos_max) = sphere_cone_cos(&center, radius, toward) else {
+                    //din interior se vede toata sfera
+                    let z = 1.0 - 2.0 * a;
+                    let r = (1.0 - z * z).max(0.0).sqrt();
+                    let (sin, cos) = (2.0 * std::f64::consts::PI * b).sin_cos();
+                    let offset = Vector3 {
+                        x: r * cos,
+                        y: r * sin,
+                        z,
+                    };
+                    return (center + offset * radius, self);
+                };
+                let cos_theta = 1.0 - a * (1.0 - cos_max);
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let (sin, cos) = (2.0 * std::f64::consts::PI * b).sin_cos();
+                let axis = to_center * (1.0 / distance);
+                let (tangent, bitangent) = orthonormal_basis(&axis);
+                let direction =
+                    tangent * (sin_theta * cos) + bitangent * (sin_theta * sin) + axis * cos_theta;
+                //prima intersectie a directiei cu sfera
+                let along = distance * cos_theta;
+                let half_chord = (radius * radius - distance * distance + along * along)
+                    .max(0.0)
+                    .sqrt();
+                *toward + direction * (along - half_chord)
+            }
+            AreaShape::Rect { center, u, v } => center + u * (2.0 * a - 1.0) + v * (2.0 * b - 1.0),
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => {
+                let (tangent, bitangent) = orthonormal_basis(&normal.normalize());
+                let r = radius * a.sqrt();
+                let (sin, cos) = (2.0 * std::f64::consts::PI * b).sin_cos();
+                center + tangent * (r * cos) + bitangent * (r * sin)
+            }
+            AreaShape::Triangle { vertices } => {
+                let s = a.sqrt();
+                vertices[0] * (1.0 - s) + vertices[1] * (s * (1.0 - b)) + vertices[2] * (s * b)
+            }
+            AreaShape::Compound { .. } => unreachable!("compound shapes pick a part first"),
+        };
+        (point, self)
     }
-}
 
-impl Scene {
-    pub fn trace(&self, ray: &Ray) -> Option<Intersection> {
-        self.objects
-            .iter()
-            .filter_map(|o| o.intersect(ray).map(|d| Intersection::new(d, o)))
-            .min_by(|i1, i2| i1.distance.partial_cmp(&i2.distance).unwrap())
+    //bucatile plate ale unui obiect emisiv; o singura bucata ramane forma ei
+    pub(crate) fn compound(mut parts: Vec<AreaShape>) -> AreaShape {
+        if parts.len() == 1 {
+            return parts.pop().unwrap();
+        }
+        let bounds: Vec<Option<Aabb>> = parts.iter().map(|part| Some(part.bounds())).collect();
+        AreaShape::Compound {
+            areas: Distribution1D::new(parts.iter().map(|part| part.area()).collect()),
+            bvh: Bvh::build(&bounds),
+            parts,
+        }
     }
 
-    pub fn get_color(&self, ray: &Ray, intersection: &Intersection) -> Color {
-        let intersection_point: Vector3 = ray.origin + (ray.direction * intersection.distance);
-        let surface_normal = intersection.object.surface_normal(&intersection_point);
-        let texture_coords = intersection.object.texture_coords(&intersection_point);
+    pub fn bounds(&self) -> Aabb {
+        match *self {
+            AreaShape::Sphere { center, radius } | AreaShape::Disk { center, radius, .. } => {
+                let extent = Vector3 {
+                    x: radius,
+                    y: radius,
+                    z: radius,
+                };
+                Aabb::new(center - extent, center + extent)
+            }
+            AreaShape::Rect { center, u, v } => [u + v, u - v, -u + v, -u - v]
+                .iter()
+                .fold(Aabb::empty(), |bounds, corner| {
+                    bounds.grow(&(center + *corner))
+                }),
+            AreaShape::Triangle { ref vertices } => vertices
+                .iter()
+                .fold(Aabb::empty(), |bounds, vertex| bounds.grow(vertex)),
+            AreaShape::Compound { ref bvh, .. } => bvh.bounds().unwrap_or_else(Aabb::empty),
+        }
+    }
 
-        let mut color = Color {
-            red: 0.0,
-            green: 0.0,
-            blue: 0.0,
+    //aceeasi forma dupa transformare; sfera si discul raman ce sunt doar la o scalare
+    //uniforma, altfel sunt aproximate prin triunghiuri
+    pub fn transformed(&self, transform: &Transform) -> Vec<AreaShape> {
+        let triangle = |vertices: [Vector3; 3]| AreaShape::Triangle {
+            vertices: vertices
+                .map(|vertex| Vector3::from(transform.point_to_world(&Point3::from(vertex)))),
         };
-        for light in &self.lights {
-            let dir_to_light = light.dir_to_light(&intersection_point);
-            //shadow acne, nudge ca sa nu trasam din interiorul obiectului afara
-            let outside_intersection_point = intersection_point + (surface_normal * 1e-7);
-            let shadow_checker = Ray {
-                origin: outside_intersection_point,
-                direction: dir_to_light,
-            };
+        match *self {
+            AreaShape::Sphere { center, radius } => match transform.uniform_scale() {
+                Some(scale) => vec![AreaShape::Sphere {
+                    center: Vector3::from(transform.point_to_world(&Point3::from(center))),
+                    radius: radius * scale,
+                }],
+                None => sphere_triangles(&center, radius)
+                    .into_iter()
+                    .map(triangle)
+                    .collect(),
+            },
+            AreaShape::Disk {
+                center,
+                normal,
+                radius,
+            } => match transform.uniform_scale() {
+                Some(scale) => vec![AreaShape::Disk {
+                    center: Vector3::from(transform.point_to_world(&Point3::from(center))),
+                    normal: Vector3::from(transform.normal_to_world(&Normal3::from(normal))),
+                    radius: radius * scale,
+                }],
+                None => disk_triangles(&center, &normal, radius)
+                    .into_iter()
+                    .map(triangle)
+                    .collect(),
+            },
+            //dupa o forfecare laturile nu mai sunt perpendiculare
+            AreaShape::Rect { center, u, v } => vec![
+                triangle([center - u - v, center + u - v, center + u + v]),
+                triangle([center - u - v, center + u + v, center - u + v]),
+            ],
+            AreaShape::Triangle { vertices } => vec![triangle(vertices)],
+            AreaShape::Compound { ref parts, .. } => parts
+                .iter()
+                .flat_map(|part| part.transformed(transform))
+                .collect(),
+        }
+    }
+}
 
-            let lit = light.lit(&self.trace(&shadow_checker), &intersection_point);
-            let light_intensity = if !lit {
-                AMBIENT_LIGHT_INTENSITY
-            } else {
-                (surface_normal.dot(&dir_to_light) as f32).max(0.0)
-                    * light.intensity(&intersection_point)
-            };
+const TESSELLATION_SEGMENTS: usize = 32;
+
+fn sphere_triangles(center: &Vector3, radius: f64) -> Vec<[Vector3; 3]> {
+    let rings = TESSELLATION_SEGMENTS / 2;
+    let point = |ring: usize, segment: usize| {
+        let theta = std::f64::consts::PI * ring as f64 / rings as f64;
+        let phi = 2.0 * std::f64::consts::PI * segment as f64 / TESSELLATION_SEGMENTS as f64;
+        *center
+            + Vector3 {
+                x: theta.sin() * phi.cos(),
+                y: theta.cos(),
+                z: theta.sin() * phi.sin(),
+            } * radius
+    };
+    let mut triangles = Vec::new();
+    for ring in 0..rings {
+        for segment in 0..TESSELLATION_SEGMENTS {
+            let corners = [
+                point(ring, segment),
+                point(ring, segment + 1),
+                point(ring + 1, segment + 1),
+                point(ring + 1, segment),
+            ];
+            //la poli doua colturi coincid si ramane un singur triunghi
+            if ring > 0 {
+                triangles.push([corners[0], corners[1], corners[2]]);
+            }
+            if ring + 1 < rings {
+                triangles.push([corners[0], corners[2], corners[3]]);
+            }
+        }
+    }
+    triangles
+}
 
-            let light_reflected = intersection.object.albedo() / std::f32::consts::PI;
+fn disk_triangles(center: &Vector3, normal: &Vector3, radius: f64) -> Vec<[Vector3; 3]> {
+    let (tangent, bitangent) = orthonormal_basis(&normal.normalize());
+    let point = |segment: usize| {
+        let (sin, cos) =
+            (2.0 * std::f64::consts::PI * segment as f64 / TESSELLATION_SEGMENTS as f64).sin_cos();
+        *center + (tangent * cos + bitangent * sin) * radius
+    };
+    (0..TESSELLATION_SEGMENTS)
+        .map(|segment| [*center, point(segment), point(segment + 1)])
+        .collect()
+}
 
-            //println!("int {:?} refl {:?}", light_intensity, light_reflected);
+//cosinusul semi-unghiului conului sub care se vede sfera din `from`; None din interior
+pub(crate) fn sphere_cone_cos(center: &Vector3, radius: f64, from: &Vector3) -> Option<f64> {
+    let distance_squared = (*center - *from).dot(&(*center - *from));
+    if distance_squared <= radius * radius {
+        return None;
+    }
+    Some((1.0 - radius * radius / distance_squared).max(0.0).sqrt())
+}
 
-            color = color
-                + intersection.object.color(&texture_coords).clone()
-                    * light.color().clone()
-                    * light_intensity
-                    * light_reflected;
+//doua axe perpendiculare pe normal si intre ele
+pub(crate) fn orthonormal_basis(normal: &Vector3) -> (Vector3, Vector3) {
+    let helper = if normal.x.abs() < 0.9 {
+        Vector3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
         }
-        color.clamp()
-    }
+    } else {
+        Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }
+    };
+    let tangent = normal.cross(&helper).normalize();
+    (tangent, normal.cross(&tangent))
+}
+
+impl Light {
+    //intensitatea fara ambient, cu scadere 1/d² pentru luminile cu pozitie
+    pub fn physical_intensity(&self, intersection_point: &Vector3) -> f32 {
+        match *self {
+            Light::Directional(ref dlight) => dlight.intensity,
+            Light::Point(ref plight) => {
+                inverse_square_falloff(plight.intensity, &plight.point, intersection_point)
+            }
+            Light::Spot(ref slight) => {
+                inverse_square_falloff(slight.intensity, &slight.point, intersection_point)
+                    * slight.cone_falloff(intersection_point)
+            }
+            Light::Area(ref alight) => {
+                inverse_square_falloff(alight.intensity, &alight.shape.center(), intersection_point)
+            }
+        }
+    }
+
+    //modelul legacy: 1/(4π·d) si ambient inclus
+    pub fn intensity(&self, intersection_point: &Vector3) -> f32 {
+        match *self {
+            Light::Directional(ref dlight) => dlight.intensity + AMBIENT_LIGHT_INTENSITY,
+            Light::Point(ref plight) => {
+                point_light_falloff(plight.intensity, &plight.point, intersection_point)
+                    + AMBIENT_LIGHT_INTENSITY
+            }
+            Light::Spot(ref slight) => {
+                point_light_falloff(slight.intensity, &slight.point, intersection_point)
+                    * slight.cone_falloff(intersection_point)
+                    + AMBIENT_LIGHT_INTENSITY
+            }
+            //o singura evaluare trateaza lumina de suprafata ca punct in centrul ei
+            Light::Area(ref alight) => {
+                point_light_falloff(alight.intensity, &alight.shape.center(), intersection_point)
+                    + AMBIENT_LIGHT_INTENSITY
+            }
+        }
+    }
+
+    pub fn color(&self) -> &Color {
+        match *self {
+            Light::Directional(ref dlight) => &dlight.color,
+            Light::Point(ref plight) => &plight.color,
+            Light::Spot(ref slight) => &slight.color,
+            Light::Area(ref alight) => &alight.color,
+        }
+    }
+
+    pub fn dir_to_light(&self, intersection_point: &Vector3) -> Vector3 {
+        match *self {
+            Light::Directional(ref dlight) => -dlight.direction.normalize(),
+            Light::Point(ref plight) => (plight.point - *intersection_point).normalize(),
+            Light::Spot(ref slight) => (slight.point - *intersection_point).normalize(),
+            Light::Area(ref alight) => (alight.shape.center() - *intersection_point).normalize(),
+        }
+    }
+
+    pub fn lit(
+        &self,
+        traced_shadow_checker: &Option<Intersection>,
+        intersection_point: &Vector3,
+    ) -> bool {
+        match *self {
+            Light::Directional(ref _dlight) => traced_shadow_checker.is_none(),
+            Light::Point(ref plight) => {
+                traced_shadow_checker.is_none()
+                    || traced_shadow_checker.as_ref().unwrap().distance
+                        > (plight.point - *intersection_point).norm()
+            }
+            //in afara conului punctul e tratat ca fiind in umbra
+            Light::Spot(ref slight) => {
+                slight.cone_falloff(intersection_point) > 0.0
+                    && (traced_shadow_checker.is_none()
+                        || traced_shadow_checker.as_ref().unwrap().distance
+                            > (slight.point - *intersection_point).norm())
+            }
+            Light::Area(ref alight) => {
+                traced_shadow_checker.is_none()
+                    || traced_shadow_checker.as_ref().unwrap().distance
+                        > (alight.shape.center() - *intersection_point).norm()
+            }
+        }
+    }
+}
+
+//fetele unei cutii ca dreptunghiuri, rotite odata cu ea
+fn box_faces(
+    center: &Vector3,
+    half_extents: &Vector3,
+    rotation: Option<&Vector3>,
+) -> Vec<AreaShape> {
+    let rotate = |v: Vector3| match rotation {
+        Some(rotation) => v.rotate_euler(rotation),
+        None => v,
+    };
+    let mut faces = Vec::new();
+    for axis in 0..3 {
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut u = Vector3::zero();
+        u[u_axis] = half_extents[u_axis];
+        let mut v = Vector3::zero();
+        v[v_axis] = half_extents[v_axis];
+        for sign in [-1.0, 1.0] {
+            let mut offset = Vector3::zero();
+            offset[axis] = sign * half_extents[axis];
+            faces.push(AreaShape::Rect {
+                center: *center + rotate(offset),
+                u: rotate(u),
+                v: rotate(v),
+            });
+        }
+    }
+    faces
+}
+
+impl Element {
+    pub fn material(&self) -> &Material {
+        match *self {
+            Element::Sphere(ref s) => &s.material,
+            Element::Cube(ref c) => &c.material,
+            Element::Box(ref b) => &b.material,
+            Element::Plane(ref p) => &p.material,
+            Element::Triangle(ref t) => &t.material,
+            Element::Mesh(ref m) => &m.material,
+            Element::Transformed(ref t) => t.element.material(),
+            Element::Instance(ref i) => match i.target {
+                Some(ref target) => target.material(),
+                None => &UNRESOLVED_MATERIAL,
+            },
+        }
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        match *self {
+            Element::Sphere(ref mut s) => Some(&mut s.material),
+            Element::Cube(ref mut c) => Some(&mut c.material),
+            Element::Box(ref mut b) => Some(&mut b.material),
+            Element::Plane(ref mut p) => Some(&mut p.material),
+            Element::Triangle(ref mut t) => Some(&mut t.material),
+            Element::Mesh(ref mut m) => Some(&mut m.material),
+            Element::Transformed(ref mut t) => t.element.material_mut(),
+            //materialul prototipului e incarcat o singura data, prin scene.prototypes
+            Element::Instance(_) => None,
+        }
+    }
+
+    //o singura lumina de suprafata pe forma obiectului, cu radianta materialului emisiv.
+    //planele (infinite) se vad, dar nu lumineaza
+    pub fn emitter(&self) -> Option<Light> {
+        let emission = match self.material().emission {
+            Some(ref emission) if emission.strength > 0.0 => emission,
+            _ => return None,
+        };
+        //bucatile degenerate (arie zero sau NaN) nu emit si n-au normala
+        let shapes: Vec<AreaShape> = self
+            .emitter_shapes()
+            .into_iter()
+            .filter(|shape| shape.area() > 0.0)
+            .collect();
+        if shapes.is_empty() {
+            return None;
+        }
+        let shape = AreaShape::compound(shapes);
+        //puterea care da exact radianta materialului, vezi AreaLight::radiance
+        let intensity = emission.strength as f64 * std::f64::consts::PI * shape.emitting_area();
+        Some(Light::Area(AreaLight {
+            shape,
+            color: emission.color.clone(),
+            intensity: intensity as f32,
+            samples: EMITTER_SAMPLES,
+            visible: false,
+        }))
+    }
+
+    //bucatile suprafetei obiectului, in spatiul lumii
+    fn emitter_shapes(&self) -> Vec<AreaShape> {
+        match *self {
+            Element::Sphere(ref s) => vec![AreaShape::Sphere {
+                center: s.center,
+                radius: s.radius,
+            }],
+            Element::Cube(ref c) => box_faces(&c.center, &c.half_extents(), None),
+            Element::Box(ref b) => box_faces(&b.center, &b.half_extents, b.rotation.as_ref()),
+            Element::Triangle(ref t) => vec![AreaShape::Triangle {
+                vertices: t.vertices,
+            }],
+            Element::Mesh(ref m) => m
+                .data
+                .indices
+                .iter()
+                .map(|i| AreaShape::Triangle {
+                    vertices: [
+                        m.data.positions[i[0]],
+                        m.data.positions[i[1]],
+                        m.data.positions[i[2]],
+                    ],
+                })
+                .collect(),
+            Element::Plane(_) => Vec::new(),
+            Element::Transformed(ref t) => t
+                .element
+                .emitter_shapes()
+                .iter()
+                .flat_map(|shape| shape.transformed(&t.transform))
+                .collect(),
+            Element::Instance(ref i) => match i.target {
+                Some(ref target) => target
+                    .emitter_shapes()
+                    .iter()
+                    .flat_map(|shape| shape.transformed(&i.transform))
+                    .collect(),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    //citeste mesh-urile si texturile referite prin path, cautate in base_dir
+    pub fn load_assets(&mut self, base_dir: &Path) -> Result<(), SceneError> {
+        match *self {
+            Element::Mesh(ref mut mesh) if !mesh.is_loaded() => mesh.load(base_dir)?,
+            Element::Transformed(ref mut t) => return t.element.load_assets(base_dir),
+            _ => {}
+        }
+        if let Some(material) = self.material_mut() {
+            if let Coloration::Texture(ref mut texture) = material.coloration {
+                if !texture.is_loaded() {
+                    texture.load_image(base_dir)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //primul asset care inca nu a fost citit de load_assets
+    fn unloaded_asset(&self) -> Option<String> {
+        match *self {
+            Element::Mesh(ref mesh) if !mesh.is_loaded() => {
+                return Some(format!("mesh {:?} is not loaded", mesh.path));
+            }
+            Element::Transformed(ref t) => return t.element.unloaded_asset(),
+            Element::Instance(ref i) => {
+                return match i.target {
+                    Some(_) => None,
+                    None => Some(format!("instance of {:?} is not resolved", i.prototype)),
+                };
+            }
+            _ => {}
+        }
+        match self.material().coloration {
+            Coloration::Texture(ref texture) if !texture.is_loaded() => {
+                Some(format!("texture {:?} is not loaded", texture.path))
+            }
+            _ => None,
+        }
+    }
+
+    fn release_instances(&mut self) {
+        match *self {
+            Element::Transformed(ref mut t) => t.element.release_instances(),
+            Element::Instance(ref mut i) => i.target = None,
+            _ => {}
+        }
+    }
+
+    //leaga instantele de prototipurile cu acelasi nume
+    pub fn resolve_instances(&mut self, prototypes: &[Prototype]) -> Result<(), SceneError> {
+        match *self {
+            Element::Transformed(ref mut t) => t.element.resolve_instances(prototypes),
+            Element::Instance(ref mut i) => {
+                let prototype = prototypes
+                    .iter()
+                    .find(|p| p.name == i.prototype)
+                    .ok_or_else(|| {
+                        SceneError::Validation(format!("unknown prototype {:?}", i.prototype))
+                    })?;
+                i.target = Some(Arc::clone(&prototype.element));
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn color(&self, hit: &SurfacePoint) -> Color {
+        self.material()
+            .coloration
+            .color(&self.texture_coords(hit), &hit.point)
+    }
+
+    //culoarea in punctul lovit de ray; cu mipmap, filtrata pe cat din textura acopera pixelul
+    pub fn surface_color(&self, ray: &Ray, hit: &SurfacePoint) -> Color {
+        let texture_coords = self.texture_coords(hit);
+        let footprint = match self.material().coloration {
+            Coloration::Texture(ref texture) if texture.mipmap != MipmapMode::None => {
+                ray.differential.as_ref().and_then(|differential| {
+                    self.texture_footprint(differential, hit, &texture_coords)
+                })
+            }
+            _ => None,
+        };
+        self.material()
+            .coloration
+            .filtered_color(&texture_coords, &hit.point, footprint.as_ref())
+    }
+
+    //razele vecine intersectate cu planul tangent, apoi diferente finite in coordonatele de textura
+    fn texture_footprint(
+        &self,
+        differential: &RayDifferential,
+        hit: &SurfacePoint,
+        texture_coords: &TextureCoords,
+    ) -> Option<TextureFootprint> {
+        let normal = self.surface_normal(hit);
+        let on_plane = |origin: &Vector3, direction: &Vector3| {
+            let denominator = normal.dot(direction);
+            if denominator.abs() < 1e-12 {
+                return None;
+            }
+            let distance = normal.dot(&(hit.point - *origin)) / denominator;
+            Some(self.surface_point_near(hit, &(*origin + *direction * distance)))
+        };
+        let dx = self.texture_coords(&on_plane(
+            &differential.dx_origin,
+            &differential.dx_direction,
+        )?);
+        let dy = self.texture_coords(&on_plane(
+            &differential.dy_origin,
+            &differential.dy_direction,
+        )?);
+        //pe sfera u sare de la 1 la 0 pe cusatura: saltul e de fapt o diferenta mica.
+        //planele si cutiile au coordonate fara cusatura, acolo diferenta ramane intreaga
+        let seam = self.has_u_seam();
+        let delta_u = |to: f32, from: f32| {
+            let d = to - from;
+            if seam {
+                d - d.round()
+            } else {
+                d
+            }
+        };
+        Some(TextureFootprint {
+            du_dx: delta_u(dx.x, texture_coords.x),
+            dv_dx: dx.y - texture_coords.y,
+            du_dy: delta_u(dy.x, texture_coords.x),
+            dv_dy: dy.y - texture_coords.y,
+        })
+    }
+
+    fn has_u_seam(&self) -> bool {
+        match *self {
+            Element::Sphere(_) => true,
+            Element::Transformed(ref t) => t.element.has_u_seam(),
+            Element::Instance(ref i) => i.target.as_ref().is_some_and(|t| t.has_u_seam()),
+            _ => false,
+        }
+    }
+
+    pub fn albedo(&self) -> f32 {
+        self.material().albedo
+    }
+
+    //None pentru obiectele infinite, care raman in afara bvh-ului
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        match *self {
+            Element::Sphere(ref s) => {
+                let r = Vector3 {
+                    x: s.radius,
+                    y: s.radius,
+                    z: s.radius,
+                };
+                Some(Aabb::new(s.center - r, s.center + r))
+            }
+            Element::Cube(ref c) => {
+                let half = c.sidelength / 2.0;
+                let h = Vector3 {
+                    x: half,
+                    y: half,
+                    z: half,
+                };
+                Some(Aabb::new(c.center - h, c.center + h))
+            }
+            Element::Box(ref b) => {
+                let h = b.half_extents;
+                let mut bounds = Aabb::empty();
+                for (sx, sy, sz) in [
+                    (-1.0, -1.0, -1.0),
+                    (-1.0, -1.0, 1.0),
+                    (-1.0, 1.0, -1.0),
+                    (-1.0, 1.0, 1.0),
+                    (1.0, -1.0, -1.0),
+                    (1.0, -1.0, 1.0),
+                    (1.0, 1.0, -1.0),
+                    (1.0, 1.0, 1.0),
+                ] {
+                    let corner = Vector3 {
+                        x: h.x * sx,
+                        y: h.y * sy,
+                        z: h.z * sz,
+                    };
+                    let corner = match b.rotation {
+                        Some(ref rotation) => corner.rotate_euler(rotation),
+                        None => corner,
+                    };
+                    bounds = bounds.grow(&(b.center + corner));
+                }
+                Some(bounds)
+            }
+            Element::Plane(_) => None,
+            Element::Triangle(ref t) => Some(triangle_bounds(&t.vertices)),
+            Element::Mesh(ref m) => m.bvh.bounds(),
+            Element::Transformed(ref t) => t
+                .element
+                .bounding_box()
+                .map(|b| t.transform.bounds_to_world(&b)),
+            Element::Instance(ref i) => i
+                .target
+                .as_ref()
+                .and_then(|target| target.bounding_box())
+                .map(|b| i.transform.bounds_to_world(&b)),
+        }
+    }
+
+    pub fn surface(&self) -> &SurfaceType {
+        &self.material().surface
+    }
+}
+
+impl Intersectable for Element {
+    fn intersect(&self, ray: &Ray) -> Option<f64> {
+        match *self {
+            Element::Sphere(ref s) => s.intersect(ray),
+            Element::Cube(ref c) => c.intersect(ray),
+            Element::Box(ref b) => b.intersect(ray),
+            Element::Plane(ref p) => p.intersect(ray),
+            Element::Triangle(ref t) => t.intersect(ray),
+            Element::Mesh(ref m) => m.intersect(ray),
+            Element::Transformed(ref t) => t.intersect(ray),
+            Element::Instance(ref i) => i.intersect(ray),
+        }
+    }
+
+    fn hit(&self, ray: &Ray) -> Option<Hit> {
+        match *self {
+            Element::Sphere(ref s) => s.hit(ray),
+            Element::Cube(ref c) => c.hit(ray),
+            Element::Box(ref b) => b.hit(ray),
+            Element::Plane(ref p) => p.hit(ray),
+            Element::Triangle(ref t) => t.hit(ray),
+            Element::Mesh(ref m) => m.hit(ray),
+            Element::Transformed(ref t) => t.hit(ray),
+            Element::Instance(ref i) => i.hit(ray),
+        }
+    }
+
+    fn texture_coords(&self, hit: &SurfacePoint) -> TextureCoords {
+        match *self {
+            Element::Sphere(ref s) => s.texture_coords(hit),
+            Element::Cube(ref c) => c.texture_coords(hit),
+            Element::Box(ref b) => b.texture_coords(hit),
+            Element::Plane(ref p) => p.texture_coords(hit),
+            Element::Triangle(ref t) => t.texture_coords(hit),
+            Element::Mesh(ref m) => m.texture_coords(hit),
+            Element::Transformed(ref t) => t.texture_coords(hit),
+            Element::Instance(ref i) => i.texture_coords(hit),
+        }
+    }
+
+    fn surface_normal(&self, hit: &SurfacePoint) -> Vector3 {
+        match *self {
+            Element::Sphere(ref s) => s.surface_normal(hit),
+            Element::Cube(ref c) => c.surface_normal(hit),
+            Element::Box(ref b) => b.surface_normal(hit),
+            Element::Plane(ref p) => p.surface_normal(hit),
+            Element::Triangle(ref t) => t.surface_normal(hit),
+            Element::Mesh(ref m) => m.surface_normal(hit),
+            Element::Transformed(ref t) => t.surface_normal(hit),
+            Element::Instance(ref i) => i.surface_normal(hit),
+        }
+    }
+
+    fn surface_point_near(&self, hit: &SurfacePoint, point: &Vector3) -> SurfacePoint {
+        match *self {
+            Element::Sphere(ref s) => s.surface_point_near(hit, point),
+            Element::Cube(ref c) => c.surface_point_near(hit, point),
+            Element::Box(ref b) => b.surface_point_near(hit, point),
+            Element::Plane(ref p) => p.surface_point_near(hit, point),
+            Element::Triangle(ref t) => t.surface_point_near(hit, point),
+            Element::Mesh(ref m) => m.surface_point_near(hit, point),
+            Element::Transformed(ref t) => t.surface_point_near(hit, point),
+            Element::Instance(ref i) => i.surface_point_near(hit, point),
+        }
+    }
+}
+
+fn validate_element(element: &Element) -> Result<(), String> {
+    match *element {
+        Element::Sphere(ref s) if s.radius <= 0.0 => {
+            return Err("sphere radius must be positive".to_string());
+        }
+        Element::Cube(ref c) if c.sidelength <= 0.0 => {
+            return Err("cube sidelength must be positive".to_string());
+        }
+        Element::Box(ref b)
+            if b.half_extents.x <= 0.0 || b.half_extents.y <= 0.0 || b.half_extents.z <= 0.0 =>
+        {
+            return Err("box half extents must be positive".to_string());
+        }
+        Element::Plane(ref p) if Vector3::from(p.normal).norm() == 0.0 => {
+            return Err("plane normal is zero".to_string());
+        }
+        Element::Transformed(ref t) => return validate_element(&t.element),
+        _ => {}
+    }
+    if let Some(ref emission) = element.material().emission {
+        if emission.strength < 0.0 {
+            return Err("emission strength must not be negative".to_string());
+        }
+    }
+    match element.material().surface {
+        SurfaceType::Refractive { index, .. } | SurfaceType::Dielectric { index, .. }
+            if index <= 0.0 =>
+        {
+            Err("index of refraction must be positive".to_string())
+        }
+        SurfaceType::Conductor { roughness }
+        | SurfaceType::Dielectric { roughness, .. }
+        | SurfaceType::Principled { roughness, .. }
+            if !(0.0..=1.0).contains(&roughness) =>
+        {
+            Err("roughness must be between 0 and 1".to_string())
+        }
+        SurfaceType::Principled { metallic, .. } if !(0.0..=1.0).contains(&metallic) => {
+            Err("metallic must be between 0 and 1".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Scene {
+    pub fn new(
+        width: u32,
+        height: u32,
+        camera: Camera,
+        lights: Vec<Light>,
+        objects: Vec<Element>,
+    ) -> Scene {
+        let mut scene = Scene::empty(width, height, camera, lights, objects);
+        scene.build_bvh();
+        scene
+    }
+
+    //restul campurilor la valorile implicite; bvh-ul ramane de construit
+    fn empty(
+        width: u32,
+        height: u32,
+        camera: Camera,
+        lights: Vec<Light>,
+        objects: Vec<Element>,
+    ) -> Scene {
+        Scene {
+            camera,
+            width,
+            height,
+            lights,
+            objects,
+            max_recursion_depth: DEFAULT_MAX_RECURSION_DEPTH,
+            sampling: Sampling::default(),
+            light_model: LightModel::default(),
+            ambient: default_ambient(),
+            background: Background::default(),
+            render_mode: RenderMode::default(),
+            custom_integrator: None,
+            sun: None,
+            emitters: Vec::new(),
+            prototypes: Vec::new(),
+            bvh: None,
+        }
+    }
+
+    pub fn objects(&self) -> &[Element] {
+        &self.objects
+    }
+
+    pub fn set_objects(&mut self, objects: Vec<Element>) {
+        self.objects = objects;
+        self.build_bvh();
+    }
+
+    pub fn add_object(&mut self, object: Element) {
+        self.objects.push(object);
+        self.build_bvh();
+    }
+
+    pub fn extend_objects(&mut self, objects: impl IntoIterator<Item = Element>) {
+        self.objects.extend(objects);
+        self.build_bvh();
+    }
+
+    pub fn remove_object(&mut self, index: usize) -> Element {
+        let object = self.objects.remove(index);
+        self.build_bvh();
+        object
+    }
+
+    //citeste texturile si mesh-urile referite prin path, apoi reconstruieste bvh-ul
+    //base_dir e directorul fisierului json, fata de care sunt date path-urile mesh-urilor,
+    //texturilor si ale hartii de mediu
+    pub fn load_assets(&mut self, base_dir: &Path) -> Result<(), SceneError> {
+        self.background.load(base_dir)?;
+        //instantele vechi tin referinte la prototipuri, le eliberam ca sa le putem modifica
+        for object in self.objects.iter_mut() {
+            object.release_instances();
+        }
+        for prototype in self.prototypes.iter_mut().rev() {
+            if let Some(element) = Arc::get_mut(&mut prototype.element) {
+                element.release_instances();
+            }
+        }
+        //un prototip poate folosi doar prototipurile definite inaintea lui
+        for i in 0..self.prototypes.len() {
+            let (previous, rest) = self.prototypes.split_at_mut(i);
+            let prototype = &mut rest[0];
+            if let Some(element) = Arc::get_mut(&mut prototype.element) {
+                element.load_assets(base_dir)?;
+                element.resolve_instances(previous)?;
+            }
+        }
+        for object in self.objects.iter_mut() {
+            object.load_assets(base_dir)?;
+            object.resolve_instances(&self.prototypes)?;
+        }
+        self.build_bvh();
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), SceneError> {
+        let invalid = |message: String| Err(SceneError::Validation(message));
+
+        if self.width == 0 || self.height == 0 {
+            return invalid(format!(
+                "image size must be positive, got {}x{}",
+                self.width, self.height
+            ));
+        }
+        if !(self.camera.fov > 0.0 && self.camera.fov < 180.0) {
+            return invalid(format!(
+                "camera fov must be between 0 and 180 degrees, got {}",
+                self.camera.fov
+            ));
+        }
+        if (self.camera.target - self.camera.eye).norm() == 0.0 {
+            return invalid("camera eye and target are the same point".to_string());
+        }
+
+        if let Background::Sky(ref sky) = self.background {
+            if sky.sun_direction.norm() == 0.0 {
+                return invalid("sky sun direction is zero".to_string());
+            }
+            if sky.turbidity < 1.0 {
+                return invalid(format!(
+                    "sky turbidity must be at least 1, got {}",
+                    sky.turbidity
+                ));
+            }
+        }
+        for (i, light) in self.lights.iter().enumerate() {
+            if let Light::Area(ref alight) = *light {
+                //comparatiile cu > 0 resping si valorile NaN
+                let has_surface = match alight.shape {
+                    AreaShape::Sphere { radius, .. } => radius > 0.0,
+                    AreaShape::Rect { u, v, .. } => u.cross(&v).norm() > 0.0,
+                    AreaShape::Disk { normal, radius, .. } => radius > 0.0 && normal.norm() > 0.0,
+                    AreaShape::Triangle { .. } | AreaShape::Compound { .. } => {
+                        alight.shape.area() > 0.0
+                    }
+                };
+                if !has_surface {
+                    return invalid(format!("light {}: area light shape has no surface", i));
+                }
+            }
+            if let Light::Spot(ref slight) = *light {
+                if slight.direction.norm() == 0.0 {
+                    return invalid(format!("light {}: spot direction is zero", i));
+                }
+                if !(0.0 <= slight.inner_angle
+                    && slight.inner_angle <= slight.outer_angle
+                    && slight.outer_angle <= 180.0)
+                {
+                    return invalid(format!(
+                        "light {}: spot angles must satisfy 0 <= inner <= outer <= 180, got {} and {}",
+                        i, slight.inner_angle, slight.outer_angle
+                    ));
+                }
+            }
+        }
+        for (i, object) in self.objects.iter().enumerate() {
+            if let Err(message) = validate_element(object) {
+                return invalid(format!("object {}: {}", i, message));
+            }
+        }
+        for prototype in self.prototypes.iter() {
+            if let Err(message) = validate_element(&prototype.element) {
+                return invalid(format!("prototype {:?}: {}", prototype.name, message));
+            }
+        }
+        self.check_loaded()
+    }
+
+    //o scena deserializata direct (fara load_assets) ar iesi cu texturi negre si fara
+    //mesh-uri sau instante; render-ele refuza sa o deseneze
+    pub fn check_loaded(&self) -> Result<(), SceneError> {
+        let unloaded = |message: String| {
+            Err(SceneError::Validation(format!(
+                "{}; call Scene::load_assets before rendering",
+                message
+            )))
+        };
+        if let Background::Environment(ref environment) = self.background {
+            if !environment.is_loaded() {
+                return unloaded(format!(
+                    "environment map {:?} is not loaded",
+                    environment.path
+                ));
+            }
+        }
+        for (i, object) in self.objects.iter().enumerate() {
+            if let Some(message) = object.unloaded_asset() {
+                return unloaded(format!("object {}: {}", i, message));
+            }
+        }
+        for prototype in self.prototypes.iter() {
+            if let Some(message) = prototype.element.unloaded_asset() {
+                return unloaded(format!("prototype {:?}: {}", prototype.name, message));
+            }
+        }
+        if self.bvh.is_none() {
+            return unloaded("the scene BVH is not built".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn update_sun(&mut self) {
+        self.sun = self.background.sun_light();
+    }
+
+    //reface tot ce depinde de lista de obiecte: bvh-ul si luminile emisive
+    pub fn build_bvh(&mut self) {
+        let bounds: Vec<Option<Aabb>> = self.objects.iter().map(|o| o.bounding_box()).collect();
+        self.bvh = Some(Bvh::build(&bounds));
+        self.emitters = self.objects.iter().filter_map(|o| o.emitter()).collect();
+    }
+
+    //luminile din json, soarele cerului si obiectele emisive
+    pub fn all_lights(&self) -> impl Iterator<Item = &Light> {
+        self.lights
+            .iter()
+            .chain(self.sun.iter())
+            .chain(self.emitters.iter())
+    }
+
+    //cel mai apropiat obiect lovit de raza; o scena fara bvh (nici load_assets, nici
+    //build_bvh) e o eroare, nu o scena goala
+    pub fn trace(&self, ray: &Ray) -> Result<Option<Intersection<'_>>, SceneError> {
+        if self.bvh.is_none() {
+            return Err(SceneError::Validation(
+                "the scene BVH is not built; call Scene::load_assets before tracing".to_string(),
+            ));
+        }
+        Ok(self.closest_hit(ray))
+    }
+
+    //pentru randare: render-ele verifica check_loaded inainte, deci bvh-ul exista
+    pub(crate) fn closest_hit(&self, ray: &Ray) -> Option<Intersection<'_>> {
+        self.bvh
+            .as_ref()?
+            .closest_hit(ray, |i| self.objects[i].hit(ray))
+            .map(|(i, hit)| Intersection::new(hit, &self.objects[i]))
+    }
+
+    //culoarea finala a pixelului (x, y), folosita de toate render-ele
+    pub fn render_pixel(&self, x: u32, y: u32) -> Color {
+        let mut sampler = Sampler::for_pixel(self.sampling.seed, x, y);
+        if self.sampling.samples_per_pixel <= 1 {
+            let ray = Ray::create_prime(x, y, self);
+            return self.camera_ray_color(&ray, &mut sampler).clamp();
+        }
+
+        let filter = self.sampling.filter;
+        let mut color = Color::black();
+        let mut weight_sum = 0.0;
+        let differential_scale = 1.0 / (self.sampling.samples_per_pixel as f64).sqrt();
+        for (dx, dy) in self.sampling.pixel_offsets(&mut sampler) {
+            let mut ray = Ray::create_prime_at(x as f64 + 0.5 + dx, y as f64 + 0.5 + dy, self);
+            ray.scale_differential(differential_scale);
+            let weight = filter.weight(dx, dy);
+            color = color + self.camera_ray_color(&ray, &mut sampler) * weight as f32;
+            weight_sum += weight;
+        }
+
+        //mitchell are lobi negativi, ne asiguram ca nu impartim la ~0
+        if weight_sum.abs() < 1e-9 {
+            return Color::black();
+        }
+        (color * (1.0 / weight_sum) as f32).clamp()
+    }
+
+    pub fn integrator(&self) -> &dyn Integrator {
+        match self.custom_integrator {
+            Some(ref integrator) => integrator.as_ref(),
+            None => self.render_mode.integrator(),
+        }
+    }
+
+    fn camera_ray_color(&self, ray: &Ray, sampler: &mut Sampler) -> Color {
+        self.integrator().radiance(self, ray, sampler)
+    }
+
+    //sampler-ul e folosit pentru efectele stocastice (umbrele luminilor de suprafata)
+    pub fn cast_ray(&self, ray: &Ray, depth: u32, sampler: &mut Sampler) -> Color {
+        //depth porneste de la 0, deci raza cu depth == max_recursion_depth ar fi in plus
+        if depth >= self.max_recursion_depth {
+            return Color::black();
+        }
+
+        let hit = self.closest_hit(ray);
+        if let Some((distance, color)) = self.trace_area_lights(ray) {
+            if hit.as_ref().is_none_or(|h| distance < h.distance) {
+                return color;
+            }
+        }
+        match hit {
+            Some(intersection) => self.get_color(ray, &intersection, depth, sampler),
+            None => self.background.color(&ray.direction),
+        }
+    }
+
+    //luminile de suprafata vizibile apar ca geometrie emisiva; nu arunca umbra
+    fn trace_area_lights(&self, ray: &Ray) -> Option<(f64, Color)> {
+        self.intersect_area_lights(ray, true)
+            .map(|(d, alight)| (d, alight.color.clone()))
+    }
+
+    //cea mai apropiata lumina de suprafata lovita de raza
+    pub(crate) fn intersect_area_lights(
+        &self,
+        ray: &Ray,
+        only_visible: bool,
+    ) -> Option<(f64, &AreaLight)> {
+        self.lights
+            .iter()
+            .filter_map(|light| match *light {
+                Light::Area(ref alight) if alight.visible || !only_visible => {
+                    alight.shape.intersect(ray).map(|d| (d, alight))
+                }
+                _ => None,
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    pub fn get_color(
+        &self,
+        ray: &Ray,
+        intersection: &Intersection,
+        depth: u32,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let hit = intersection.surface_point(ray);
+        let intersection_point = hit.point;
+        let surface_normal = intersection.object.surface_normal(&hit);
+        //partea opaca se lumineaza pe fata dinspre privitor (planele au doua fete)
+        let facing = surface_normal.face_forward(&ray.direction);
+
+        let color = match *intersection.object.surface() {
+            SurfaceType::Diffuse => {
+                self.shade_diffuse(ray, intersection.object, &hit, &facing, sampler)
+            }
+            SurfaceType::Reflective { reflectivity } => {
+                let diffuse_color =
+                    self.shade_diffuse(ray, intersection.object, &hit, &facing, sampler);
+                let reflection_ray =
+                    Ray::create_reflection(facing, ray.direction, intersection_point, SHADOW_BIAS);
+                diffuse_color * (1.0 - reflectivity)
+                    + self.cast_ray(&reflection_ray, depth + 1, sampler) * reflectivity
+            }
+            SurfaceType::Refractive {
+                index,
+                transparency,
+            } => {
+                let diffuse_color =
+                    self.shade_diffuse(ray, intersection.object, &hit, &facing, sampler);
+                let transmitted_color = self.shade_transmission(
+                    ray,
+                    intersection,
+                    &surface_normal,
+                    index as f64,
+                    depth,
+                    sampler,
+                );
+                diffuse_color * (1.0 - transparency) + transmitted_color * transparency
+            }
+            SurfaceType::Conductor { .. }
+            | SurfaceType::Dielectric { .. }
+            | SurfaceType::Principled { .. } => {
+                self.shade_scattered(ray, intersection, &surface_normal, depth, sampler)
+            }
+        };
+        match intersection.object.material().emission {
+            Some(ref emission) => (color + emission.radiance()).clamp(),
+            None => color.clamp(),
+        }
+    }
+
+    fn shade_transmission(
+        &self,
+        ray: &Ray,
+        intersection: &Intersection,
+        surface_normal: &Vector3,
+        index: f64,
+        depth: u32,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let object = intersection.object;
+        let hit = intersection.surface_point(ray);
+        let intersection_point = &hit.point;
+        //normala intoarsa spre raza, indicii inversati cand iesim din obiect
+        let (normal, eta_i, eta_t) = if object.inside(ray, &hit) {
+            (-*surface_normal, index, 1.0)
+        } else {
+            (*surface_normal, 1.0, index)
+        };
+
+        let kr = fresnel(ray.direction, normal, eta_i, eta_t) as f32;
+
+        let mut refraction_color = Color::black();
+        if kr < 1.0 {
+            if let Some(transmission_ray) = Ray::create_transmission(
+                normal,
+                ray.direction,
+                *intersection_point,
+                SHADOW_BIAS,
+                eta_i,
+                eta_t,
+            ) {
+                refraction_color = self.cast_ray(&transmission_ray, depth + 1, sampler);
+            }
+        }
+
+        let reflection_ray =
+            Ray::create_reflection(normal, ray.direction, *intersection_point, SHADOW_BIAS);
+        let reflection_color = self.cast_ray(&reflection_ray, depth + 1, sampler);
+
+        let surface_color = object.surface_color(ray, &hit);
+        (reflection_color * kr + refraction_color * (1.0 - kr)) * surface_color
+    }
+
+    //lumina primita de la harta de mediu, cu directiile alese dupa luminozitatea ei,
+    //inmultita cu reflectance pe fiecare directie
+    fn environment_lighting(
+        &self,
+        environment: &EnvironmentMap,
+        origin: &Vector3,
+        surface_normal: &Vector3,
+        reflectance: &dyn Fn(&Vector3) -> Color,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let samples = environment.samples.max(1);
+        let mut lighting = Color::black();
+        for _ in 0..samples {
+            let (direction, radiance, pdf) = match environment.sample(sampler) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            let cos = surface_normal.dot(&direction);
+            if cos <= 0.0 {
+                continue;
+            }
+            let shadow_checker = Ray {
+                origin: *origin,
+                direction,
+                differential: None,
+            };
+            if self.closest_hit(&shadow_checker).is_none() {
+                lighting = lighting + radiance * reflectance(&direction) * (cos / pdf) as f32;
+            }
+        }
+        lighting * (1.0 / samples as f32)
+    }
+
+    pub fn shade_diffuse(
+        &self,
+        ray: &Ray,
+        object: &Element,
+        hit: &SurfacePoint,
+        surface_normal: &Vector3,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let albedo_color = object.surface_color(ray, hit) * object.albedo();
+        let light_reflected = albedo_color.clone() * (1.0 / std::f32::consts::PI);
+        let view = -ray.direction;
+        let mut color = self.shade_direct(
+            &hit.point,
+            surface_normal,
+            &light_reflected,
+            &|incoming| match object.material().specular {
+                Some(ref highlight) => {
+                    light_reflected.clone() + highlight.eval(&view, incoming, surface_normal)
+                }
+                None => light_reflected.clone(),
+            },
+            sampler,
+        );
+        //lumina ambientala uniforma reflectata de o suprafata lambert: albedo * ambient
+        if self.light_model == LightModel::Physical {
+            color = color + albedo_color * self.ambient.clone();
+        }
+        color
+    }
+
+    //lumina directa intr-un punct al unei suprafete cu Bsdf; fara raze secundare
+    pub fn shade_bsdf(
+        &self,
+        ray: &Ray,
+        object: &Element,
+        hit: &SurfacePoint,
+        surface_normal: &Vector3,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let bsdf = Bsdf::at(object, ray, hit);
+        let outgoing = -ray.direction;
+        //luminile sunt cautate pe partea dinspre privitor
+        let facing = surface_normal.face_forward(&ray.direction);
+        let mut color = self.shade_direct(
+            &hit.point,
+            &facing,
+            &(bsdf.reflectance() * (1.0 / std::f32::consts::PI)),
+            &|incoming| bsdf.eval(&outgoing, incoming, surface_normal),
+            sampler,
+        );
+        if self.light_model == LightModel::Physical {
+            color = color + bsdf.reflectance() * self.ambient.clone();
+        }
+        color
+    }
+
+    //lumina directa plus o raza aleasa dupa Bsdf (reflexie lucioasa sau refractie)
+    fn shade_scattered(
+        &self,
+        ray: &Ray,
+        intersection: &Intersection,
+        surface_normal: &Vector3,
+        depth: u32,
+        sampler: &mut Sampler,
+    ) -> Color {
+        let object = intersection.object;
+        let hit = intersection.surface_point(ray);
+        let intersection_point = hit.point;
+        let color = self.shade_bsdf(ray, object, &hit, surface_normal, sampler);
+
+        let bsdf = Bsdf::at(object, ray, &hit);
+        match bsdf.sample(&-ray.direction, surface_normal, sampler) {
+            Some(sample) => {
+                let scattered_ray = Ray::create_scattered(
+                    *surface_normal,
+                    sample.direction,
+                    intersection_point,
+                    SHADOW_BIAS,
+                );
+                color + self.cast_ray(&scattered_ray, depth + 1, sampler) * sample.weight
+            }
+            None => color,
+        }
+    }
+
+    //suma peste lumini (si harta de mediu) a luminii incidente, inmultita cu
+    //reflectance(directia spre lumina); in umbra ramane doar lumina ambientala (Legacy),
+    //reflectata difuz de `diffuse`, fara highlight
+    fn shade_direct(
+        &self,
+        intersection_point: &Vector3,
+        surface_normal: &Vector3,
+        diffuse: &Color,
+        reflectance: &dyn Fn(&Vector3) -> Color,
+        sampler: &mut Sampler,
+    ) -> Color {
+        //shadow acne, nudge ca sa nu trasam din interiorul obiectului afara
+        let outside_intersection_point = *intersection_point + (*surface_normal * SHADOW_BIAS);
+
+        let mut color = Color::black();
+        for light in self.all_lights() {
+            let light_reflected = match *light {
+                //media mai multor puncte de pe suprafata luminii, fiecare tratat ca o lumina
+                //punctuala; punctele acoperite partial dau penumbra
+                Light::Area(ref alight) => {
+                    let samples = alight.samples.max(1);
+                    let mut sum = Color::black();
+                    for _ in 0..samples {
+                        let (sample_point, part) =
+                            alight.shape.sample_point(intersection_point, sampler);
+                        let dir_to_light = (sample_point - *intersection_point).normalize();
+                        let shadow_checker = Ray {
+                            origin: outside_intersection_point,
+                            direction: dir_to_light,
+                            differential: None,
+                        };
+                        let lit = self.closest_hit(&shadow_checker).is_none_or(|shadow| {
+                            shadow.distance
+                                > (sample_point - *intersection_point).norm()
+                                    * (1.0 - LIGHT_SURFACE_EPSILON)
+                        });
+                        sum = sum
+                            + if !lit {
+                                diffuse.clone() * self.light_model.shadowed_intensity()
+                            } else {
+                                reflectance(&dir_to_light)
+                                    * (surface_normal.dot(&dir_to_light) as f32).max(0.0)
+                                    * alight.intensity_at(
+                                        &sample_point,
+                                        part,
+                                        intersection_point,
+                                        self.light_model,
+                                    )
+                            };
+                    }
+                    sum * (1.0 / samples as f32)
+                }
+                _ => {
+                    let dir_to_light = light.dir_to_light(intersection_point);
+                    let shadow_checker = Ray {
+                        origin: outside_intersection_point,
+                        direction: dir_to_light,
+                        differential: None,
+                    };
+
+                    let lit = light.lit(&self.closest_hit(&shadow_checker), intersection_point);
+                    if !lit {
+                        diffuse.clone() * self.light_model.shadowed_intensity()
+                    } else {
+                        let intensity = match self.light_model {
+                            LightModel::Legacy => light.intensity(intersection_point),
+                            LightModel::Physical => light.physical_intensity(intersection_point),
+                        };
+                        reflectance(&dir_to_light)
+                            * ((surface_normal.dot(&dir_to_light) as f32).max(0.0) * intensity)
+                    }
+                }
+            };
+
+            color = color + light.color().clone() * light_reflected;
+        }
+        if let Background::Environment(ref environment) = self.background {
+            if environment.lighting {
+                color = color
+                    + self.environment_lighting(
+                        environment,
+                        &outside_intersection_point,
+                        surface_normal,
+                        reflectance,
+                        sampler,
+                    );
+            }
+        }
+        color
+    }
+}
+
+//materialul, obiectele si scena folosite de teste; camera e in origine, cu fov 90, spre -z
+#[cfg(test)]
+pub(crate) fn test_material(color: Color, albedo: f32, surface: SurfaceType) -> Material {
+    Material {
+        coloration: Coloration::Color(color),
+        albedo,
+        surface,
+        specular: None,
+        emission: None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_sphere(center: Vector3, radius: f64, material: Material) -> Element {
+    Element::Sphere(Sphere {
+        center,
+        radius,
+        material,
+    })
+}
+
+#[cfg(test)]
+pub(crate) fn test_plane(p: Vector3, normal: Normal3, material: Material) -> Element {
+    Element::Plane(Plane {
+        p,
+        normal,
+        material,
+    })
+}
+
+#[cfg(test)]
+pub(crate) fn test_scene(
+    width: u32,
+    height: u32,
+    lights: Vec<Light>,
+    objects: Vec<Element>,
+) -> crate::scenemanager::SceneManager {
+    crate::scenemanager::SceneManager::new(width, height, 90.0, Vector3::zero(), lights, objects)
+}
+
+#[test]
+fn test_facing_mirrors_stop_at_max_depth() {
+    let mirror = |z: f64, nz: f64| {
+        test_plane(
+            Vector3 { x: 0.0, y: 0.0, z },
+            Normal3 {
+                x: 0.0,
+                y: 0.0,
+                z: nz,
+            },
+            Material {
+                //fiecare oglinda lovita adauga 0.1, deci culoarea numara razele
+                emission: Some(Emission {
+                    color: Color::gray(1.0),
+                    strength: 0.1,
+                }),
+                ..test_material(
+                    Color::gray(1.0),
+                    1.0,
+                    SurfaceType::Reflective { reflectivity: 1.0 },
+                )
+            },
+        )
+    };
+    let mut scenemgr = test_scene(1, 1, Vec::new(), vec![mirror(-1.0, 1.0), mirror(1.0, -1.0)]);
+    let ray = Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    };
+    for depth in [1, 3, 5] {
+        scenemgr.set_max_recursion_depth(depth);
+        let color = scenemgr.scene.cast_ray(&ray, 0, &mut Sampler::new(0));
+        assert!((color.red - 0.1 * depth as f32).abs() < 1e-5);
+    }
+    scenemgr.set_max_recursion_depth(0);
+    let color = scenemgr.scene.cast_ray(&ray, 0, &mut Sampler::new(0));
+    assert_eq!(color.red, 0.0);
+}
+
+#[test]
+fn test_spot_light_cone() {
+    let light: Light = serde_json::from_str(
+        r#"{"Spot": {"point": {"x": 0.0, "y": 5.0, "z": 0.0}, "direction": {"x": 0.0, "y": -1.0, "z": 0.0},
+            "inner_angle": 20.0, "outer_angle": 40.0,
+            "color": {"red": 1.0, "green": 1.0, "blue": 1.0}, "intensity": 100.0}}"#,
+    )
+    .unwrap();
+    let Light::Spot(ref spot) = light else {
+        panic!("expected a spot light");
+    };
+    //tan(30) * 5 ~ 2.89, la jumatatea zonei de tranzitie
+    let below = Vector3::zero();
+    let edge = Vector3 {
+        x: 5.0 * 30.0f64.to_radians().tan(),
+        y: 0.0,
+        z: 0.0,
+    };
+    let outside = Vector3 {
+        x: 10.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    assert_eq!(spot.cone_falloff(&below), 1.0);
+    let falloff = spot.cone_falloff(&edge);
+    assert!(falloff > 0.0 && falloff < 1.0);
+    assert_eq!(spot.cone_falloff(&outside), 0.0);
+
+    assert!(light.lit(&None, &below));
+    assert!(!light.lit(&None, &outside));
+    assert_eq!(light.intensity(&outside), AMBIENT_LIGHT_INTENSITY);
+    assert!(light.intensity(&below) > light.intensity(&edge));
+}
+
+#[test]
+fn test_area_light_soft_shadow() {
+    let white = || test_material(Color::gray(1.0), 1.0, SurfaceType::Diffuse);
+    let floor = || {
+        test_plane(
+            Vector3::zero(),
+            Normal3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            white(),
+        )
+    };
+    let light = || {
+        Light::Area(AreaLight {
+            shape: AreaShape::Rect {
+                center: Vector3 {
+                    x: 0.0,
+                    y: 3.0,
+                    z: 0.0,
+                },
+                u: Vector3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                v: Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+            },
+            color: Color::gray(1.0),
+            intensity: 50.0,
+            samples: 64,
+            visible: true,
+        })
+    };
+    let occluder = test_sphere(
+        Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        0.5,
+        white(),
+    );
+    let shadowed = test_scene(1, 1, vec![light()], vec![floor(), occluder]).scene;
+    let open = test_scene(1, 1, vec![light()], vec![floor()]).scene;
+
+    let up = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let down = Ray {
+        origin: up,
+        direction: -up,
+        differential: None,
+    };
+    let shade = |scene: &Scene, x: f64| {
+        let point = Vector3 { x, y: 0.0, z: 0.0 };
+        scene
+            .shade_diffuse(
+                &down,
+                &scene.objects[0],
+                &SurfacePoint::from(point),
+                &up,
+                &mut Sampler::new(7),
+            )
+            .red
+    };
+    let ambient = AMBIENT_LIGHT_INTENSITY / std::f32::consts::PI;
+    //sub sfera nu se vede niciun punct al luminii, in penumbra doar o parte
+    assert!((shade(&shadowed, 0.0) - ambient).abs() < 1e-6);
+    let penumbra = shade(&shadowed, 0.6);
+    assert!(penumbra > ambient && penumbra < shade(&open, 0.6));
+
+    let ray = Ray {
+        origin: Vector3 {
+            x: 0.0,
+            y: 5.0,
+            z: 0.0,
+        },
+        direction: -up,
+        differential: None,
+    };
+    let seen = shadowed.cast_ray(&ray, 0, &mut Sampler::new(0));
+    assert_eq!(seen.red, 1.0);
+}
+
+#[test]
+fn test_degenerate_area_light_fails_validation() {
+    let area = |shape: AreaShape| {
+        Light::Area(AreaLight {
+            shape,
+            color: Color::gray(1.0),
+            intensity: 1.0,
+            samples: 1,
+            visible: true,
+        })
+    };
+    let x = Vector3 {
+        x: 1.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let shapes = vec![
+        AreaShape::Sphere {
+            center: Vector3::zero(),
+            radius: f64::NAN,
+        },
+        AreaShape::Rect {
+            center: Vector3::zero(),
+            u: x,
+            v: Vector3::zero(),
+        },
+        AreaShape::Disk {
+            center: Vector3::zero(),
+            normal: Vector3::zero(),
+            radius: 1.0,
+        },
+        AreaShape::Triangle {
+            vertices: [Vector3::zero(), x, x * 2.0],
+        },
+    ];
+    for shape in shapes {
+        let scenemgr = test_scene(1, 1, vec![area(shape)], Vec::new());
+        match scenemgr.scene.validate() {
+            Err(SceneError::Validation(message)) => assert!(message.contains("no surface")),
+            _ => panic!("a degenerate area light must fail validation"),
+        }
+    }
+}
+
+#[test]
+fn test_physical_light_model_inverse_square() {
+    let white = Color::gray(1.0);
+    let floor = test_plane(
+        Vector3::zero(),
+        Normal3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        test_material(white.clone(), 0.5, SurfaceType::Diffuse),
+    );
+    let point_light = |height: f64| {
+        Light::Point(PointLight {
+            point: Vector3 {
+                x: 0.0,
+                y: height,
+                z: 0.0,
+            },
+            color: white.clone(),
+            intensity: 100.0,
+        })
+    };
+    let mut scenemgr = test_scene(1, 1, Vec::new(), vec![floor]);
+    scenemgr.set_light_model(LightModel::Physical);
+    let up = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let down = Ray {
+        origin: up,
+        direction: -up,
+        differential: None,
+    };
+    let shade = |scenemgr: &crate::scenemanager::SceneManager| {
+        let scene = &scenemgr.scene;
+        scene
+            .shade_diffuse(
+                &down,
+                &scene.objects[0],
+                &SurfacePoint::from(Vector3::zero()),
+                &up,
+                &mut Sampler::new(0),
+            )
+            .red
+    };
+
+    //fara lumini ramane doar ambientul, aplicat o singura data
+    let ambient = 0.5 * AMBIENT_LIGHT_INTENSITY;
+    assert!((shade(&scenemgr) - ambient).abs() < 1e-6);
+
+    scenemgr.add_light(point_light(2.0));
+    let near = shade(&scenemgr) - ambient;
+    scenemgr.remove_light(0);
+    scenemgr.add_light(point_light(4.0));
+    let far = shade(&scenemgr) - ambient;
+    assert!((near / far - 4.0).abs() < 1e-4);
+}
+
+#[test]
+fn test_blinn_phong_highlight() {
+    let material: Material = serde_json::from_str(
+        r#"{"coloration": {"Color": {"red": 1.0, "green": 1.0, "blue": 1.0}}, "albedo": 0.5}"#,
+    )
+    .unwrap();
+    assert!(material.specular.is_none());
+    let mut material: Material = serde_json::from_str(
+        r#"{"coloration": {"Color": {"red": 1.0, "green": 1.0, "blue": 1.0}}, "albedo": 0.5,
+            "specular": {"shininess": 32.0}}"#,
+    )
+    .unwrap();
+    assert_eq!(material.specular.as_ref().unwrap().weight, 1.0);
+
+    let up = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let light = || {
+        Light::Point(PointLight {
+            point: Vector3 {
+                x: 1.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            color: Color::gray(1.0),
+            intensity: 10.0,
+        })
+    };
+    let highlight = material.specular.take();
+    let floor = |specular: Option<BlinnPhong>| {
+        test_plane(
+            Vector3::zero(),
+            up.into(),
+            Material {
+                specular,
+                ..test_material(Color::gray(1.0), 0.5, SurfaceType::Diffuse)
+            },
+        )
+    };
+    let shade = |specular: Option<BlinnPhong>, eye: Vector3| {
+        let mut scenemgr = test_scene(1, 1, vec![light()], vec![floor(specular)]);
+        scenemgr.set_light_model(LightModel::Physical);
+        scenemgr.set_ambient(Color::black());
+        let scene = &scenemgr.scene;
+        let ray = Ray {
+            origin: eye,
+            direction: (Vector3::zero() - eye).normalize(),
+            differential: None,
+        };
+        scene
+            .shade_diffuse(
+                &ray,
+                &scene.objects[0],
+                &SurfacePoint::from(Vector3::zero()),
+                &up,
+                &mut Sampler::new(0),
+            )
+            .red
+    };
+
+    //in directia oglinzii, highlight / difuz = (n + 8) / (8 * albedo) = 10
+    let mirror = Vector3 {
+        x: -1.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let diffuse = shade(None, mirror);
+    let shiny = shade(highlight.clone(), mirror);
+    assert!((shiny / diffuse - 11.0).abs() < 1e-3);
+    //privit de sus, highlight-ul scade cu cos(22.5)^32
+    let above = shade(highlight.clone(), up);
+    assert!(above < shiny && above / diffuse < 2.0);
+
+    //in umbra (Legacy) ramane doar lumina ambientala difuza, fara highlight
+    let shadowed = |specular: Option<BlinnPhong>| {
+        let blocker = test_sphere(
+            Vector3 {
+                x: 0.5,
+                y: 0.5,
+                z: 0.0,
+            },
+            0.2,
+            test_material(Color::gray(1.0), 0.5, SurfaceType::Diffuse),
+        );
+        let scenemgr = test_scene(1, 1, vec![light()], vec![floor(specular), blocker]);
+        let scene = &scenemgr.scene;
+        let ray = Ray {
+            origin: mirror,
+            direction: (-mirror).normalize(),
+            differential: None,
+        };
+        scene
+            .shade_diffuse(
+                &ray,
+                &scene.objects[0],
+                &SurfacePoint::from(Vector3::zero()),
+                &up,
+                &mut Sampler::new(0),
+            )
+            .red
+    };
+    let expected = 0.5 / std::f32::consts::PI * AMBIENT_LIGHT_INTENSITY;
+    assert!((shadowed(None) - expected).abs() < 1e-6);
+    assert!((shadowed(highlight) - expected).abs() < 1e-6);
+}
+
+#[test]
+fn test_emissive_triangle_matches_area_light() {
+    use crate::integrator::Integrator;
+    use crate::pathtracer::PathTracer;
+
+    //triunghi emisiv deasupra podelei; aceeasi forma ca lumina de suprafata de comparatie
+    let vertices = [
+        Vector3 {
+            x: -1.0,
+            y: 2.0,
+            z: -1.0,
+        },
+        Vector3 {
+            x: 1.0,
+            y: 2.0,
+            z: -1.0,
+        },
+        Vector3 {
+            x: 0.0,
+            y: 2.0,
+            z: 1.0,
+        },
+    ];
+    let lamp: Element = serde_json::from_str(&format!(
+        r#"{{"Triangle": {{"vertices": {}, "material": {{"coloration": {{"Color": {{"red": 0.0, "green": 0.0, "blue": 0.0}}}},
+            "albedo": 0.0, "emission": {{"color": {{"red": 1.0, "green": 0.5, "blue": 0.25}}, "strength": 4.0}}}}}}}}"#,
+        serde_json::to_string(&vertices).unwrap()
+    ))
+    .unwrap();
+    let floor = || {
+        test_plane(
+            Vector3::zero(),
+            Normal3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            test_material(Color::gray(1.0), 0.8, SurfaceType::Diffuse),
+        )
+    };
+    let scene_with = |lights: Vec<Light>, objects: Vec<Element>| {
+        let mut scenemgr = test_scene(1, 1, lights, objects);
+        scenemgr.set_light_model(LightModel::Physical);
+        scenemgr.set_ambient(Color::black());
+        scenemgr.scene
+    };
+    let emissive = scene_with(Vec::new(), vec![floor(), lamp]);
+    assert_eq!(emissive.emitters.len(), 1);
+    let shape = AreaShape::Triangle { vertices };
+    let equivalent = Light::Area(AreaLight {
+        intensity: (4.0 * std::f64::consts::PI * shape.emitting_area()) as f32,
+        shape,
+        color: Color {
+            red: 1.0,
+            green: 0.5,
+            blue: 0.25,
+        },
+        samples: EMITTER_SAMPLES,
+        visible: false,
+    });
+    let reference = scene_with(vec![equivalent], vec![floor()]);
+
+    let up = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let down = Ray {
+        origin: up * 1.5,
+        direction: -up,
+        differential: None,
+    };
+    let point = Vector3 {
+        x: 0.3,
+        y: 0.0,
+        z: 0.2,
+    };
+    let shade = |scene: &Scene| {
+        scene.shade_diffuse(
+            &down,
+            &scene.objects[0],
+            &SurfacePoint::from(point),
+            &up,
+            &mut Sampler::new(2),
+        )
+    };
+    //umbra triunghiului asupra propriilor puncte nu trebuie sa conteze
+    let (lit, expected) = (shade(&emissive), shade(&reference));
+    assert!(lit.red > 0.0);
+    assert!((lit.red - expected.red).abs() <= 1e-4 * expected.red);
+    assert!((lit.blue - expected.blue).abs() <= 1e-4 * expected.blue);
+
+    //privit direct, triunghiul are radianta materialului, in ambele integratoare
+    let up_ray = Ray {
+        origin: Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: -0.2,
+        },
+        direction: up,
+        differential: None,
+    };
+    let seen = emissive.cast_ray(&up_ray, 0, &mut Sampler::new(0));
+    assert_eq!((seen.red, seen.green, seen.blue), (1.0, 1.0, 1.0));
+    let traced = PathTracer::default().radiance(&emissive, &up_ray, &mut Sampler::new(0));
+    assert!((traced.red - 4.0).abs() < 1e-5 && (traced.blue - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_emissive_mesh_is_one_light() {
+    //patrat 2x2 din trei triunghiuri inegale (ariile 1, 2, 1), instantiat la y = 2
+    let quad: Element = serde_json::from_str(
+        r#"{"Mesh": {"data": {"positions": [{"x": -1.0, "y": 0.0, "z": -1.0}, {"x": 1.0, "y": 0.0, "z": -1.0},
+            {"x": 1.0, "y": 0.0, "z": 1.0}, {"x": -1.0, "y": 0.0, "z": 1.0}, {"x": 0.0, "y": 0.0, "z": -1.0}],
+            "indices": [[4, 1, 2], [4, 2, 3], [4, 3, 0]]},
+            "material": {"coloration": {"Color": {"red": 0.0, "green": 0.0, "blue": 0.0}}, "albedo": 0.0,
+                "emission": {"color": {"red": 1.0, "green": 1.0, "blue": 1.0}, "strength": 2.0}}}}"#,
+    )
+    .unwrap();
+    let floor = || {
+        test_plane(
+            Vector3::zero(),
+            Normal3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            test_material(Color::gray(1.0), 0.8, SurfaceType::Diffuse),
+        )
+    };
+    let lifted = Transform::new(
+        Vector3 {
+            x: 0.0,
+            y: 2.0,
+            z: 0.0,
+        },
+        Vector3::zero(),
+        Vector3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        },
+    )
+    .unwrap();
+    let mut scenemgr = test_scene(1, 1, Vec::new(), vec![floor()]);
+    scenemgr.set_light_model(LightModel::Physical);
+    scenemgr.set_ambient(Color::black());
+    scenemgr.add_prototype("quad", quad);
+    scenemgr.add_instance("quad", lifted).unwrap();
+    let emissive = scenemgr.scene;
+
+    assert_eq!(emissive.emitters.len(), 1);
+    let light = match emissive.emitters[0] {
+        Light::Area(ref alight) => alight,
+        _ => panic!("expected an area light"),
+    };
+    assert!(matches!(light.shape, AreaShape::Compound { .. }));
+    assert_eq!(light.samples, EMITTER_SAMPLES);
+    assert!((light.shape.area() - 4.0).abs() < 1e-9);
+
+    //triunghiul din mijloc are jumatate din arie, deci primeste jumatate din puncte
+    let mut sampler = Sampler::new(1);
+    let count = 4000;
+    let mut middle = 0;
+    for _ in 0..count {
+        let (point, part) = light.shape.sample_point(&Vector3::zero(), &mut sampler);
+        assert!((point.y - 2.0).abs() < 1e-9 && point.x.abs() <= 1.0 && point.z.abs() <= 1.0);
+        if (part.area() - 2.0).abs() < 1e-9 {
+            middle += 1;
+        }
+    }
+    assert!((middle as f64 / count as f64 - 0.5).abs() < 0.03);
+
+    //acelasi patrat ca lumina de suprafata, cu aceeasi radianta
+    let shape = AreaShape::Rect {
+        center: Vector3 {
+            x: 0.0,
+            y: 2.0,
+            z: 0.0,
+        },
+        u: Vector3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        v: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        },
+    };
+    let mut reference = test_scene(
+        1,
+        1,
+        vec![Light::Area(AreaLight {
+            intensity: (2.0 * std::f64::consts::PI * shape.emitting_area()) as f32,
+            shape,
+            color: Color::gray(1.0),
+            samples: EMITTER_SAMPLES,
+            visible: false,
+        })],
+        vec![floor()],
+    );
+    reference.set_light_model(LightModel::Physical);
+    reference.set_ambient(Color::black());
+
+    let down = Ray {
+        origin: Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        direction: Vector3 {
+            x: 0.0,
+            y: -1.0,
+            z: 0.0,
+        },
+        differential: None,
+    };
+    let point = Vector3 {
+        x: 0.4,
+        y: 0.0,
+        z: -0.3,
+    };
+    let average = |scene: &Scene| {
+        let mut sum = 0.0;
+        for seed in 0..200 {
+            sum += scene
+                .shade_diffuse(
+                    &down,
+                    &scene.objects[0],
+                    &SurfacePoint::from(point),
+                    &-down.direction,
+                    &mut Sampler::new(seed),
+                )
+                .red;
+        }
+        sum / 200.0
+    };
+    let (lit, expected) = (average(&emissive), average(&reference.scene));
+    assert!(lit > 0.0);
+    assert!((lit - expected).abs() < 0.03 * expected);
+
+    //o sfera transformata ramane sfera la scalare uniforma, altfel devine triunghiuri
+    let scaled = |scale: Vector3| {
+        let sphere = test_sphere(
+            Vector3::zero(),
+            1.0,
+            Material {
+                emission: Some(Emission {
+                    color: Color::gray(1.0),
+                    strength: 1.0,
+                }),
+                ..test_material(Color::black(), 0.0, SurfaceType::Diffuse)
+            },
+        );
+        let element = Element::Transformed(Transformed {
+            transform: Transform::new(Vector3::zero(), Vector3::zero(), scale).unwrap(),
+            element: Box::new(sphere),
+        });
+        match element.emitter() {
+            Some(Light::Area(alight)) => alight.shape,
+            _ => panic!("transformed emissive sphere must light the scene"),
+        }
+    };
+    let uniform = scaled(Vector3 {
+        x: 2.0,
+        y: 2.0,
+        z: 2.0,
+    });
+    assert!(matches!(uniform, AreaShape::Sphere { radius, .. } if (radius - 2.0).abs() < 1e-9));
+    let flattened = scaled(Vector3 {
+        x: 1.0,
+        y: 1.0,
+        z: 1e-3,
+    });
+    assert!(matches!(flattened, AreaShape::Compound { .. }));
+    //un disc de raza 1 vazut din ambele parti
+    assert!(
+        (flattened.area() - 2.0 * std::f64::consts::PI).abs() < 0.02 * 2.0 * std::f64::consts::PI
+    );
+}
+
+#[test]
+fn test_flat_emissive_box_skips_degenerate_faces() {
+    //cutie fara grosime pe y: doar fetele de sus si de jos au arie
+    let flat: Element = serde_json::from_str(
+        r#"{"Box": {"center": {"x": 0.0, "y": 2.0, "z": 0.0}, "half_extents": {"x": 1.0, "y": 0.0, "z": 1.0},
+            "material": {"coloration": {"Color": {"red": 0.0, "green": 0.0, "blue": 0.0}}, "albedo": 0.0,
+                "emission": {"color": {"red": 1.0, "green": 1.0, "blue": 1.0}, "strength": 1.0}}}}"#,
+    )
+    .unwrap();
+    let light = match flat.emitter() {
+        Some(Light::Area(alight)) => alight,
+        _ => panic!("expected an area light"),
+    };
+    match light.shape {
+        AreaShape::Compound { ref parts, .. } => assert_eq!(parts.len(), 2),
+        _ => panic!("expected a compound shape"),
+    }
+    assert!((light.shape.area() - 8.0).abs() < 1e-9);
+    let normal = light.shape.normal_at(&Vector3 {
+        x: 0.5,
+        y: 2.0,
+        z: 0.5,
+    });
+    assert!((normal.y.abs() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_texture_footprint_on_plane_and_sphere_seam() {
+    use image::{Rgba, RgbaImage};
+
+    //tabla de sah 8x8 pe un plan, cu o unitate de lume pe textura
+    let image = RgbaImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    });
+    let texture = Texture {
+        mipmap: MipmapMode::Trilinear,
+        ..Texture::from_image(
+            PathBuf::from("checker.png"),
+            DynamicImage::ImageRgba8(image),
+        )
+    };
+    let white = || test_material(Color::gray(1.0), 1.0, SurfaceType::Diffuse);
+    let plane = test_plane(
+        Vector3::zero(),
+        Normal3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+        Material {
+            coloration: Coloration::Texture(texture),
+            ..white()
+        },
+    );
+
+    //de departe, pixelul acopera 2 unitati, adica doua repetari ale texturii
+    let hit = Vector3 {
+        x: 0.3,
+        y: 0.0,
+        z: 0.2,
+    };
+    let origin = Vector3 {
+        x: 0.3,
+        y: 10.0,
+        z: 0.2,
+    };
+    let toward = |offset: Vector3| (hit + offset - origin).normalize();
+    let ray = Ray {
+        origin,
+        direction: toward(Vector3::zero()),
+        differential: Some(RayDifferential {
+            dx_origin: origin,
+            dx_direction: toward(Vector3 {
+                x: 2.0,
+                y: 0.0,
+                z: 0.0,
+            }),
+            dy_origin: origin,
+            dy_direction: toward(Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 2.0,
+            }),
+        }),
+    };
+    let point = SurfacePoint::from(hit);
+    let footprint = plane
+        .texture_footprint(
+            ray.differential.as_ref().unwrap(),
+            &point,
+            &plane.texture_coords(&point),
+        )
+        .unwrap();
+    assert!((footprint.du_dx - 2.0).abs() < 1e-4 && footprint.dv_dx.abs() < 1e-4);
+    assert!(footprint.du_dy.abs() < 1e-4 && (footprint.dv_dy.abs() - 2.0).abs() < 1e-4);
+    //ultimul nivel de mipmap: media tablei
+    assert!((plane.surface_color(&ray, &point).red - 0.5).abs() < 1e-3);
+
+    //pe sfera, doua puncte de o parte si de alta a cusaturii au u aproape 1 si aproape 0
+    let sphere = test_sphere(Vector3::zero(), 1.0, white());
+    let angle: f64 = 1e-3;
+    let on_sphere = |z: f64| Vector3 {
+        x: -angle.cos(),
+        y: 0.0,
+        z,
+    };
+    let origin = Vector3 {
+        x: -5.0,
+        y: 0.0,
+        z: 0.0,
+    };
+    let point = SurfacePoint::from(on_sphere(angle.sin()));
+    let differential = RayDifferential {
+        dx_origin: origin,
+        dx_direction: (on_sphere(-angle.sin()) - origin).normalize(),
+        dy_origin: origin,
+        dy_direction: (point.point - origin).normalize(),
+    };
+    let coords = sphere.texture_coords(&point);
+    assert!(coords.x > 0.99);
+    let footprint = sphere
+        .texture_footprint(&differential, &point, &coords)
+        .unwrap();
+    assert!(footprint.du_dx.abs() < 0.01);
 }
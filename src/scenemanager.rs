@@ -1,9 +1,18 @@
-use crate::render::Ray;
-use crate::scenedata::{self, Color, Element, Light, Scene};
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::error::SceneError;
+use crate::integrator::{Integrator, RenderMode};
+use crate::obj;
+use crate::parallel;
+use crate::sampling::Sampling;
+use crate::scenedata::{Color, Element, Instance, Light, LightModel, Prototype, Scene};
+use crate::transform::Transform;
 use crate::vector3::Vector3;
-use image::{DynamicImage, GenericImage, Rgba};
+use image::DynamicImage;
 use serde_json;
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 pub struct SceneManager {
     pub scene: Scene,
@@ -11,16 +20,7 @@ pub struct SceneManager {
 
 impl SceneManager {
     pub fn new_empty(width: u32, height: u32, fov: f64, ray_origin: Vector3) -> SceneManager {
-        SceneManager {
-            scene: Scene {
-                width,
-                height,
-                fov,
-                ray_origin,
-                lights: Vec::new(),
-                objects: Vec::new(),
-            },
-        }
+        SceneManager::new(width, height, fov, ray_origin, Vec::new(), Vec::new())
     }
 
     pub fn new(
@@ -32,23 +32,56 @@ impl SceneManager {
         objects: Vec<Element>,
     ) -> SceneManager {
         SceneManager {
-            scene: Scene {
-                width,
-                height,
-                fov,
-                ray_origin,
-                lights,
-                objects,
-            },
+            scene: Scene::new(width, height, Camera::new(ray_origin, fov), lights, objects),
         }
     }
 
-    pub fn new_from_scene(scene: Scene) -> SceneManager {
-        SceneManager { scene }
+    //base_dir e directorul fata de care sunt date path-urile din scena, ca la load_from_json
+    pub fn new_from_scene(mut scene: Scene, base_dir: &Path) -> Result<SceneManager, SceneError> {
+        scene.update_sun();
+        scene.load_assets(base_dir)?;
+        scene.validate()?;
+        Ok(SceneManager { scene })
     }
 
     pub fn set_ray_origin(&mut self, origin: Vector3) {
-        self.scene.ray_origin = origin;
+        self.scene.camera.move_to(origin);
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.scene.camera = camera;
+    }
+
+    pub fn set_max_recursion_depth(&mut self, depth: u32) {
+        self.scene.max_recursion_depth = depth;
+    }
+
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        self.scene.sampling = sampling;
+    }
+
+    pub fn set_light_model(&mut self, light_model: LightModel) {
+        self.scene.light_model = light_model;
+    }
+
+    pub fn set_ambient(&mut self, ambient: Color) {
+        self.scene.ambient = ambient;
+    }
+
+    //un cer preetham aduce si lumina soarelui
+    pub fn set_background(&mut self, background: Background) {
+        self.scene.background = background;
+        self.scene.update_sun();
+    }
+
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.scene.render_mode = render_mode;
+        self.scene.custom_integrator = None;
+    }
+
+    //integrator propriu, folosit in locul celui din render_mode; nu se salveaza in json
+    pub fn set_integrator(&mut self, integrator: Arc<dyn Integrator>) {
+        self.scene.custom_integrator = Some(integrator);
     }
 
     pub fn add_light(&mut self, light: Light) {
@@ -60,46 +93,266 @@ impl SceneManager {
     }
 
     pub fn add_object(&mut self, object: Element) {
-        self.scene.objects.push(object);
+        self.scene.add_object(object);
     }
 
     pub fn remove_object(&mut self, index: usize) {
-        self.scene.objects.remove(index);
+        self.scene.remove_object(index);
+    }
+
+    pub fn add_prototype(&mut self, name: &str, element: Element) {
+        self.scene.prototypes.push(Prototype {
+            name: name.to_string(),
+            element: Arc::new(element),
+        });
+    }
+
+    //prototipul trebuie adaugat inainte cu add_prototype
+    pub fn add_instance(
+        &mut self,
+        prototype: &str,
+        transform: Transform,
+    ) -> Result<(), SceneError> {
+        let mut instance = Element::Instance(Instance {
+            prototype: prototype.to_string(),
+            transform,
+            target: None,
+        });
+        instance.resolve_instances(&self.scene.prototypes)?;
+        self.add_object(instance);
+        Ok(())
+    }
+
+    //fiecare material din .mtl devine un Mesh separat; intoarce cate obiecte s-au adaugat
+    pub fn load_obj(
+        &mut self,
+        file_path: &str,
+        transform: Option<&Transform>,
+    ) -> Result<usize, SceneError> {
+        let elements = obj::load_obj(Path::new(file_path), transform)?;
+        let count = elements.len();
+        self.scene.extend_objects(elements);
+        Ok(count)
     }
 
-    pub fn save_to_json(&mut self, file_path: &str) {
-        let json_data =
-            serde_json::to_string_pretty(&self.scene).expect("Failed to serialize scene");
-        fs::write(file_path, json_data).expect("Failed to write JSON file");
+    pub fn save_to_json(&self, file_path: &str) -> Result<(), SceneError> {
+        let json_data = serde_json::to_string_pretty(&self.scene).map_err(SceneError::Serialize)?;
+        fs::write(file_path, json_data).map_err(|source| SceneError::Io {
+            path: PathBuf::from(file_path),
+            source,
+        })
     }
 
-    pub fn load_from_json(file_path: &str) -> Result<SceneManager, serde_json::Error> {
-        let json_data = fs::read_to_string(file_path).expect("Failed to read JSON file");
-        println!("loading json scene");
-        let scenetest: Scene = serde_json::from_str(&json_data).expect("Failed to parse JSON coi");
-        let scene: Scene = serde_json::from_str(&json_data)?;
+    pub fn load_from_json(file_path: &str) -> Result<SceneManager, SceneError> {
+        let path = PathBuf::from(file_path);
+        let json_data = fs::read_to_string(&path).map_err(|source| SceneError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let mut scene: Scene = serde_json::from_str(&json_data)
+            .map_err(|err| SceneError::from_json(path.clone(), err))?;
+        scene.load_assets(path.parent().unwrap_or(Path::new("")))?;
+        scene.validate()?;
         Ok(SceneManager { scene })
     }
 
-    pub fn render(&self) -> DynamicImage {
-        let mut img = DynamicImage::new_rgb8(self.scene.width, self.scene.height);
-        let black = Rgba([0, 0, 0, 255]);
-
-        for x in 0..self.scene.width {
-            for y in 0..self.scene.height {
-                let ray = Ray::create_prime(x, y, &self.scene);
-                let i = self.scene.trace(&ray);
-                if let Some(intersection) = i {
-                    img.put_pixel(
-                        x,
-                        y,
-                        Color::to_rgb(&self.scene.get_color(&ray, &intersection)),
-                    );
-                } else {
-                    img.put_pixel(x, y, black);
-                }
-            }
-        }
-        img
+    pub fn render(&self) -> Result<DynamicImage, SceneError> {
+        crate::render(&self.scene)
     }
+
+    pub fn render_parallel(&self, thread_count: usize) -> Result<DynamicImage, SceneError> {
+        parallel::render_parallel(&self.scene, thread_count)
+    }
+}
+
+#[test]
+fn test_load_legacy_scene_without_camera() {
+    let scenemgr = SceneManager::load_from_json("resources/scenetested.json")
+        .expect("failed to load legacy scene");
+    let camera = &scenemgr.scene.camera;
+    assert_eq!(camera.eye, Vector3::zero());
+    assert_eq!(camera.fov, 100.0);
+    assert_eq!(camera.target.z, -1.0);
+    assert_eq!(
+        scenemgr.scene.max_recursion_depth,
+        crate::scenedata::DEFAULT_MAX_RECURSION_DEPTH
+    );
+
+    let json = serde_json::to_string(&scenemgr.scene).unwrap();
+    let reloaded: Scene = serde_json::from_str(&json).unwrap();
+    assert_eq!(reloaded.camera.eye, camera.eye);
+    assert_eq!(reloaded.objects().len(), scenemgr.scene.objects().len());
+}
+
+#[test]
+fn test_load_errors() {
+    let dir = std::env::temp_dir().join(format!("raytracer_scene_error_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, contents: &str| {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    };
+
+    let missing = dir.join("missing.json");
+    assert!(matches!(
+        SceneManager::load_from_json(missing.to_str().unwrap()),
+        Err(SceneError::Io { .. })
+    ));
+
+    let broken = write("broken.json", "{\n  \"width\": 10,\n  \"height\": }");
+    match SceneManager::load_from_json(&broken) {
+        Err(SceneError::Parse { line, column, .. }) => assert_eq!((line, column), (3, 13)),
+        _ => panic!("expected a parse error"),
+    }
+
+    let scene = |width: u32, coloration: &str| {
+        format!(
+            r#"{{"ray_origin": {{"x": 0.0, "y": 0.0, "z": 0.0}}, "fov": 90.0,
+                "width": {}, "height": 10, "lights": [],
+                "objects": [{{"Sphere": {{"center": {{"x": 0.0, "y": 0.0, "z": -3.0}},
+                    "radius": 1.0, "material": {{"coloration": {}, "albedo": 1.0}}}}}}]}}"#,
+            width, coloration
+        )
+    };
+    let color = r#"{"Color": {"red": 1.0, "green": 1.0, "blue": 1.0}}"#;
+    let no_texture = write(
+        "no_texture.json",
+        &scene(10, r#"{"Texture": "does/not/exist.png"}"#),
+    );
+    assert!(matches!(
+        SceneManager::load_from_json(&no_texture),
+        Err(SceneError::MissingTexture { .. })
+    ));
+
+    let empty = write("empty.json", &scene(0, color));
+    assert!(matches!(
+        SceneManager::load_from_json(&empty),
+        Err(SceneError::Validation(_))
+    ));
+
+    let valid = write("valid.json", &scene(10, color));
+    assert!(SceneManager::load_from_json(&valid).is_ok());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mesh_path_is_relative_to_scene_file() {
+    let dir = std::env::temp_dir().join(format!("raytracer_mesh_path_{}", std::process::id()));
+    fs::create_dir_all(dir.join("meshes")).unwrap();
+    fs::write(
+        dir.join("meshes").join("quad.json"),
+        r#"{"positions": [{"x": -1.0, "y": -1.0, "z": -3.0}, {"x": 1.0, "y": -1.0, "z": -3.0},
+            {"x": 1.0, "y": 1.0, "z": -3.0}, {"x": -1.0, "y": 1.0, "z": -3.0}],
+            "indices": [[0, 1, 2], [0, 2, 3]]}"#,
+    )
+    .unwrap();
+    let scene_path = dir.join("scene.json");
+    fs::write(
+        &scene_path,
+        r#"{"ray_origin": {"x": 0.0, "y": 0.0, "z": 0.0}, "fov": 90.0,
+            "width": 1, "height": 1, "lights": [], "render_mode": "Normals",
+            "objects": [{"Mesh": {"path": "meshes/quad.json",
+                "material": {"coloration": {"Color": {"red": 1.0, "green": 1.0, "blue": 1.0}}, "albedo": 1.0}}}]}"#,
+    )
+    .unwrap();
+
+    //procesul ruleaza din radacina repo-ului, nu din directorul scenei
+    let scenemgr = SceneManager::load_from_json(scene_path.to_str().unwrap()).unwrap();
+    assert_eq!(scenemgr.scene.render_pixel(0, 0).blue, 1.0);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_empty_mesh_file_counts_as_loaded() {
+    let dir = std::env::temp_dir().join(format!("raytracer_empty_mesh_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("empty.json"),
+        r#"{"positions": [], "indices": []}"#,
+    )
+    .unwrap();
+    let scene_path = dir.join("scene.json");
+    fs::write(
+        &scene_path,
+        r#"{"ray_origin": {"x": 0.0, "y": 0.0, "z": 0.0}, "fov": 90.0,
+            "width": 1, "height": 1, "lights": [],
+            "objects": [{"Mesh": {"path": "empty.json",
+                "material": {"coloration": {"Color": {"red": 1.0, "green": 1.0, "blue": 1.0}}, "albedo": 1.0}}}]}"#,
+    )
+    .unwrap();
+
+    let scenemgr = SceneManager::load_from_json(scene_path.to_str().unwrap()).unwrap();
+    match scenemgr.scene.objects()[0] {
+        Element::Mesh(ref mesh) => assert!(mesh.is_loaded()),
+        _ => panic!("expected a mesh"),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_texture_paths_are_relative_to_scene_file() {
+    let dir = std::env::temp_dir().join(format!("raytracer_texture_path_{}", std::process::id()));
+    fs::create_dir_all(dir.join("textures")).unwrap();
+    fs::copy(
+        "resources/rainbowtexture.png",
+        dir.join("textures").join("rainbow.png"),
+    )
+    .unwrap();
+    fs::copy("resources/rainbowtexture.png", dir.join("sky.png")).unwrap();
+    let scene_path = dir.join("scene.json");
+    fs::write(
+        &scene_path,
+        r#"{"ray_origin": {"x": 0.0, "y": 0.0, "z": 0.0}, "fov": 90.0,
+            "width": 1, "height": 1, "lights": [],
+            "background": {"Environment": {"path": "sky.png"}},
+            "objects": [{"Sphere": {"center": {"x": 0.0, "y": 0.0, "z": -3.0}, "radius": 1.0,
+                "material": {"coloration": {"Texture": "textures/rainbow.png"}, "albedo": 1.0}}}]}"#,
+    )
+    .unwrap();
+
+    //atat textura cat si harta de mediu sunt cautate langa fisierul scenei
+    let scenemgr = SceneManager::load_from_json(scene_path.to_str().unwrap()).unwrap();
+    assert!(scenemgr.scene.validate().is_ok());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_deserialized_scene_needs_load_assets() {
+    let json = r#"{"ray_origin": {"x": 0.0, "y": 0.0, "z": 0.0}, "fov": 90.0,
+        "width": 1, "height": 1, "lights": [],
+        "objects": [{"Sphere": {"center": {"x": 0.0, "y": 0.0, "z": -3.0}, "radius": 1.0,
+            "material": {"coloration": {"Texture": "resources/rainbowtexture.png"}, "albedo": 1.0}}}]}"#;
+    let mut scene: Scene = serde_json::from_str(json).unwrap();
+    match scene.validate() {
+        Err(SceneError::Validation(message)) => assert!(message.contains("texture")),
+        _ => panic!("an unloaded texture must fail validation"),
+    }
+    assert!(matches!(
+        crate::render(&scene),
+        Err(SceneError::Validation(_))
+    ));
+    assert!(matches!(
+        parallel::render_parallel(&scene, 2),
+        Err(SceneError::Validation(_))
+    ));
+    let ray = crate::render::Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    };
+    assert!(matches!(scene.trace(&ray), Err(SceneError::Validation(_))));
+
+    scene.load_assets(Path::new("")).unwrap();
+    assert!(scene.validate().is_ok());
+    assert_eq!(crate::render(&scene).unwrap().width(), 1);
+    assert!(scene.trace(&ray).unwrap().is_some());
 }
@@ -0,0 +1,449 @@
+use crate::scenedata::{Color, TextureCoords};
+use image::{DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+//cum se combina texelii din jurul punctului cautat, pe un singur nivel
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TextureFilter {
+    #[default]
+    Nearest,
+    Bilinear,
+    //catmull-rom pe 4x4 texeli
+    Bicubic,
+}
+
+//None = mereu nivelul 0, ca inainte de mipmap-uri
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum MipmapMode {
+    #[default]
+    None,
+    //interpolare intre cele doua niveluri cele mai apropiate de marimea pixelului
+    Trilinear,
+    //mai multe sample-uri trilineare de-a lungul axei lungi a pixelului proiectat,
+    //pentru suprafetele vazute din unghi razant
+    Anisotropic,
+}
+
+//ce se citeste in afara intervalului [0, 1] al texturii
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum WrapMode {
+    #[default]
+    Repeat,
+    //se repeta oglindita, ca marginile vecine sa se potriveasca
+    Mirror,
+    //se prelungesc texelii de pe margine
+    Clamp,
+    //in afara texturii, culoarea data
+    Border(Color),
+}
+
+//aplicata coordonatelor de textura inainte de citire: scalare, rotatie (in grade, in jurul
+//originii), apoi translatie
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UvTransform {
+    #[serde(default = "default_uv_scale")]
+    pub scale: [f32; 2],
+    #[serde(default)]
+    pub offset: [f32; 2],
+    #[serde(default)]
+    pub rotation: f32,
+}
+
+fn default_uv_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+impl Default for UvTransform {
+    fn default() -> UvTransform {
+        UvTransform {
+            scale: default_uv_scale(),
+            offset: [0.0, 0.0],
+            rotation: 0.0,
+        }
+    }
+}
+
+impl UvTransform {
+    //doar scalarea si rotatia, fara translatie
+    fn linear(&self, u: f32, v: f32) -> (f32, f32) {
+        let (u, v) = (u * self.scale[0], v * self.scale[1]);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (u * cos - v * sin, u * sin + v * cos)
+    }
+
+    pub fn apply(&self, texture_coords: &TextureCoords) -> TextureCoords {
+        let (u, v) = self.linear(texture_coords.x, texture_coords.y);
+        TextureCoords {
+            x: u + self.offset[0],
+            y: v + self.offset[1],
+        }
+    }
+
+    //derivatele se transforma doar cu partea liniara
+    pub fn apply_footprint(&self, footprint: &TextureFootprint) -> TextureFootprint {
+        let (du_dx, dv_dx) = self.linear(footprint.du_dx, footprint.dv_dx);
+        let (du_dy, dv_dy) = self.linear(footprint.du_dy, footprint.dv_dy);
+        TextureFootprint {
+            du_dx,
+            dv_dx,
+            du_dy,
+            dv_dy,
+        }
+    }
+}
+
+//cat se schimba coordonatele de textura cand trecem la pixelul urmator pe x si pe y
+#[derive(Debug, Clone, Copy)]
+pub struct TextureFootprint {
+    pub du_dx: f32,
+    pub dv_dx: f32,
+    pub du_dy: f32,
+    pub dv_dy: f32,
+}
+
+const MAX_ANISOTROPY: f32 = 8.0;
+
+struct MipLevel {
+    width: usize,
+    height: usize,
+    texels: Vec<Color>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: i64, y: i64, wrap: &WrapMode) -> Color {
+        match (address(x, self.width, wrap), address(y, self.height, wrap)) {
+            (Some(x), Some(y)) => self.texels[y * self.width + x].clone(),
+            _ => match *wrap {
+                WrapMode::Border(ref color) => color.clone(),
+                _ => Color::black(),
+            },
+        }
+    }
+
+    fn sample(&self, u: f32, v: f32, filter: TextureFilter, wrap: &WrapMode) -> Color {
+        //centrul texelului i e la (i + 0.5) / width
+        let x = u * self.width as f32 - 0.5;
+        let y = v * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        match filter {
+            TextureFilter::Nearest => {
+                let (x, y) = (u * self.width as f32, v * self.height as f32);
+                match *wrap {
+                    //ca inainte: trunchiere spre zero, apoi repetare
+                    WrapMode::Repeat => self.texel(x as i64, y as i64, wrap),
+                    _ => self.texel(x.floor() as i64, y.floor() as i64, wrap),
+                }
+            }
+            TextureFilter::Bilinear => {
+                let top = self.texel(x0, y0, wrap) * (1.0 - tx) + self.texel(x0 + 1, y0, wrap) * tx;
+                let bottom = self.texel(x0, y0 + 1, wrap) * (1.0 - tx)
+                    + self.texel(x0 + 1, y0 + 1, wrap) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+            TextureFilter::Bicubic => {
+                let (wx, wy) = (catmull_rom(tx), catmull_rom(ty));
+                let mut color = Color::black();
+                for (j, weight_y) in wy.iter().enumerate() {
+                    for (i, weight_x) in wx.iter().enumerate() {
+                        color = color
+                            + self.texel(x0 - 1 + i as i64, y0 - 1 + j as i64, wrap)
+                                * (weight_x * weight_y);
+                    }
+                }
+                //lobii negativi pot iesi putin din [0, 1]
+                color.clamp()
+            }
+        }
+    }
+
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                //la dimensiuni impare ultimul rand/coloana se foloseste de doua ori
+                let (x0, y0) = (2 * x, 2 * y);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let at = |x: usize, y: usize| self.texels[y * self.width + x].clone();
+                texels.push((at(x0, y0) + at(x1, y0) + at(x0, y1) + at(x1, y1)) * 0.25);
+            }
+        }
+        MipLevel {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
+//indexul texelului citit pentru coordonata i; None = in afara texturii, pe border
+fn address(i: i64, size: usize, wrap: &WrapMode) -> Option<usize> {
+    let size = size as i64;
+    match *wrap {
+        WrapMode::Repeat => Some(i.rem_euclid(size) as usize),
+        WrapMode::Mirror => {
+            let i = i.rem_euclid(2 * size);
+            Some(if i < size { i } else { 2 * size - 1 - i } as usize)
+        }
+        WrapMode::Clamp => Some(i.clamp(0, size - 1) as usize),
+        WrapMode::Border(_) if (0..size).contains(&i) => Some(i as usize),
+        WrapMode::Border(_) => None,
+    }
+}
+
+//ponderile celor 4 texeli din jurul lui t, pentru offset-urile -1, 0, 1, 2
+fn catmull_rom(t: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        (-t3 + 2.0 * t2 - t) / 2.0,
+        (3.0 * t3 - 5.0 * t2 + 2.0) / 2.0,
+        (-3.0 * t3 + 4.0 * t2 + t) / 2.0,
+        (t3 - t2) / 2.0,
+    ]
+}
+
+//imaginea la rezolutia initiala si injumatatita succesiv pana la 1x1
+#[derive(Default)]
+pub struct MipChain {
+    levels: Vec<MipLevel>,
+}
+
+impl MipChain {
+    pub fn new(image: &DynamicImage) -> MipChain {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return MipChain::default();
+        }
+        let texels = image.pixels().map(|(_, _, p)| Color::from_rgb(p)).collect();
+        let mut levels = vec![MipLevel {
+            width: width as usize,
+            height: height as usize,
+            texels,
+        }];
+        while let Some(last) = levels.last() {
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+        MipChain { levels }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn sample(
+        &self,
+        texture_coords: &TextureCoords,
+        footprint: Option<&TextureFootprint>,
+        filter: TextureFilter,
+        mode: MipmapMode,
+        wrap: &WrapMode,
+    ) -> Color {
+        if self.levels.is_empty() {
+            return Color::black();
+        }
+        let (u, v) = (texture_coords.x, texture_coords.y);
+        let footprint = match (mode, footprint) {
+            (MipmapMode::None, _) | (_, None) => return self.levels[0].sample(u, v, filter, wrap),
+            (_, Some(footprint)) => footprint,
+        };
+
+        //derivatele in texeli de la nivelul 0
+        let (width, height) = (self.levels[0].width as f32, self.levels[0].height as f32);
+        let dx = (footprint.du_dx * width, footprint.dv_dx * height);
+        let dy = (footprint.du_dy * width, footprint.dv_dy * height);
+        let (length_x, length_y) = (dx.0.hypot(dx.1), dy.0.hypot(dy.1));
+
+        match mode {
+            MipmapMode::Anisotropic => {
+                let (major, minor, axis) = if length_x > length_y {
+                    (length_x, length_y, (footprint.du_dx, footprint.dv_dx))
+                } else {
+                    (length_y, length_x, (footprint.du_dy, footprint.dv_dy))
+                };
+                //peste MAX_ANISOTROPY sample-uri acceptam putina estompare in loc de aliasing
+                let minor = minor.max(major / MAX_ANISOTROPY);
+                let count = if minor > 0.0 {
+                    (major / minor).ceil().clamp(1.0, MAX_ANISOTROPY) as usize
+                } else {
+                    1
+                };
+                let mut color = Color::black();
+                for i in 0..count {
+                    let t = (i as f32 + 0.5) / count as f32 - 0.5;
+                    color = color
+                        + self.trilinear(u + axis.0 * t, v + axis.1 * t, minor, filter, wrap)
+                            * (1.0 / count as f32);
+                }
+                color
+            }
+            _ => self.trilinear(u, v, length_x.max(length_y), filter, wrap),
+        }
+    }
+
+    //width = cati texeli de la nivelul 0 acopera pixelul
+    fn trilinear(
+        &self,
+        u: f32,
+        v: f32,
+        width: f32,
+        filter: TextureFilter,
+        wrap: &WrapMode,
+    ) -> Color {
+        let lod = width.max(1.0).log2().min((self.levels.len() - 1) as f32);
+        let lower = lod.floor() as usize;
+        let t = lod - lower as f32;
+        let color = self.levels[lower].sample(u, v, filter, wrap);
+        if t <= 0.0 || lower + 1 >= self.levels.len() {
+            return color;
+        }
+        color * (1.0 - t) + self.levels[lower + 1].sample(u, v, filter, wrap) * t
+    }
+}
+
+#[test]
+fn test_mip_chain_filters_checkerboard() {
+    use image::{Rgba, RgbaImage};
+
+    //tabla de sah 8x8 alb-negru, un texel pe patrat
+    let image = RgbaImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    });
+    let chain = MipChain::new(&DynamicImage::ImageRgba8(image));
+    assert_eq!(chain.len(), 4);
+    let at = |u: f32, v: f32| TextureCoords { x: u, y: v };
+
+    //nearest pastreaza texelii exacti, bilinear la granita amesteca doi vecini
+    let nearest = chain.sample(
+        &at(0.01, 0.01),
+        None,
+        TextureFilter::Nearest,
+        MipmapMode::None,
+        &WrapMode::Repeat,
+    );
+    assert_eq!(nearest.red, 1.0);
+    let edge = chain.sample(
+        &at(0.125, 0.0625),
+        None,
+        TextureFilter::Bilinear,
+        MipmapMode::None,
+        &WrapMode::Repeat,
+    );
+    assert!((edge.red - 0.5).abs() < 1e-6);
+    //bicubic trece prin valorile texelilor in centrele lor
+    let center = chain.sample(
+        &at(0.1875, 0.0625),
+        None,
+        TextureFilter::Bicubic,
+        MipmapMode::None,
+        &WrapMode::Repeat,
+    );
+    assert!(center.red.abs() < 1e-6);
+
+    //pixelul acopera 4 texeli: nivelul 2, unde tabla e deja gri
+    let footprint = TextureFootprint {
+        du_dx: 0.5,
+        dv_dx: 0.0,
+        du_dy: 0.0,
+        dv_dy: 0.5,
+    };
+    let far = chain.sample(
+        &at(0.3, 0.7),
+        Some(&footprint),
+        TextureFilter::Bilinear,
+        MipmapMode::Trilinear,
+        &WrapMode::Repeat,
+    );
+    assert!((far.red - 0.5).abs() < 1e-6);
+
+    //pixel foarte alungit pe u: anizotrop ramane mai aproape de detaliul de pe v
+    let grazing = TextureFootprint {
+        du_dx: 0.5,
+        dv_dx: 0.0,
+        du_dy: 0.0,
+        dv_dy: 1.0 / 64.0,
+    };
+    let sharp = chain.sample(
+        &at(0.5, 0.0625),
+        Some(&grazing),
+        TextureFilter::Nearest,
+        MipmapMode::Anisotropic,
+        &WrapMode::Repeat,
+    );
+    let blurry = chain.sample(
+        &at(0.5, 0.0625),
+        Some(&grazing),
+        TextureFilter::Nearest,
+        MipmapMode::Trilinear,
+        &WrapMode::Repeat,
+    );
+    assert!((sharp.red - 0.5).abs() <= (blurry.red - 0.5).abs() + 1e-6);
+}
+
+#[test]
+fn test_wrap_modes_and_uv_transform() {
+    use image::{Rgba, RgbaImage};
+
+    //4x1: negru, gri inchis, gri deschis, alb
+    let image = RgbaImage::from_fn(4, 1, |x, _| {
+        let v = (x * 85) as u8;
+        Rgba([v, v, v, 255])
+    });
+    let chain = MipChain::new(&DynamicImage::ImageRgba8(image));
+    let red = |u: f32, wrap: &WrapMode| {
+        let coords = TextureCoords { x: u, y: 0.5 };
+        chain
+            .sample(
+                &coords,
+                None,
+                TextureFilter::Nearest,
+                MipmapMode::None,
+                wrap,
+            )
+            .red
+    };
+
+    //1.1 cade pe primul texel la repeat, pe ultimul la mirror si clamp
+    assert_eq!(red(1.1, &WrapMode::Repeat), 0.0);
+    assert_eq!(red(1.1, &WrapMode::Mirror), 1.0);
+    assert_eq!(red(1.4, &WrapMode::Mirror), red(0.6, &WrapMode::Repeat));
+    assert_eq!(red(7.0, &WrapMode::Clamp), 1.0);
+    assert_eq!(red(-3.0, &WrapMode::Clamp), 0.0);
+    let border = WrapMode::Border(Color {
+        red: 0.25,
+        green: 0.0,
+        blue: 0.0,
+    });
+    assert_eq!(red(-0.1, &border), 0.25);
+    assert_eq!(red(0.9, &border), 1.0);
+
+    //scalare 2 pe u, rotatie de 90 de grade, apoi translatie
+    let transform = UvTransform {
+        scale: [2.0, 1.0],
+        offset: [0.5, 0.0],
+        rotation: 90.0,
+    };
+    let moved = transform.apply(&TextureCoords { x: 0.25, y: 0.0 });
+    assert!((moved.x - 0.5).abs() < 1e-6 && (moved.y - 0.5).abs() < 1e-6);
+    let footprint = transform.apply_footprint(&TextureFootprint {
+        du_dx: 0.1,
+        dv_dx: 0.0,
+        du_dy: 0.0,
+        dv_dy: 0.1,
+    });
+    assert!((footprint.dv_dx - 0.2).abs() < 1e-6 && (footprint.du_dy + 0.1).abs() < 1e-6);
+}
@@ -0,0 +1,366 @@
+use crate::bvh::Aabb;
+use crate::error::SceneError;
+use crate::render::{Ray, RayDifferential};
+use crate::vector3::{Matrix4, Normal3, Point3, Vector3};
+use serde::{Deserialize, Serialize};
+
+//in json: fie {"matrix": [[...], ...]}, fie translation/rotation(grade)/scale,
+//aplicate in ordinea scale, rotation, translation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TransformDescription {
+    Matrix {
+        matrix: Matrix4,
+    },
+    Components {
+        #[serde(default = "Vector3::zero")]
+        translation: Vector3,
+        #[serde(default = "Vector3::zero")]
+        rotation: Vector3,
+        #[serde(default = "default_scale")]
+        scale: Vector3,
+    },
+}
+
+fn default_scale() -> Vector3 {
+    Vector3 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TransformDescription", into = "TransformDescription")]
+pub struct Transform {
+    description: TransformDescription,
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform::new(Vector3::zero(), Vector3::zero(), default_scale())
+            .expect("identity is invertible")
+    }
+
+    pub fn new(
+        translation: Vector3,
+        rotation: Vector3,
+        scale: Vector3,
+    ) -> Result<Transform, SceneError> {
+        Transform::from_description(TransformDescription::Components {
+            translation,
+            rotation,
+            scale,
+        })
+    }
+
+    pub fn from_matrix(matrix: Matrix4) -> Result<Transform, SceneError> {
+        Transform::from_description(TransformDescription::Matrix { matrix })
+    }
+
+    fn from_description(description: TransformDescription) -> Result<Transform, SceneError> {
+        let matrix = match description {
+            TransformDescription::Matrix { matrix } => matrix,
+            TransformDescription::Components {
+                translation,
+                rotation,
+                scale,
+            } => {
+                Matrix4::translation(&translation)
+                    * Matrix4::rotation_euler(&rotation)
+                    * Matrix4::scaling(&scale)
+            }
+        };
+        let inverse = matrix.inverse().ok_or_else(|| {
+            SceneError::Validation("transform matrix is not invertible".to_string())
+        })?;
+        Ok(Transform {
+            description,
+            matrix,
+            inverse,
+        })
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    pub fn inverse(&self) -> &Matrix4 {
+        &self.inverse
+    }
+
+    pub fn point_to_world(&self, point: &Point3) -> Point3 {
+        self.matrix.transform_point(point)
+    }
+
+    pub fn point_to_local(&self, point: &Point3) -> Point3 {
+        self.inverse.transform_point(point)
+    }
+
+    //directia din spatiul obiectului e normalizata, deci distantele locale trebuie
+    //impartite la scale ca sa fie distante in spatiul lumii
+    //razele vecine (pentru mipmap-uri) sunt aduse in spatiul obiectului cu aceeasi matrice
+    pub fn ray_to_local(&self, ray: &Ray) -> (Ray, f64) {
+        let direction = self.inverse.transform_vector(&ray.direction);
+        let scale = direction.norm();
+        //originile sunt puncte (se muta cu translatia), directiile nu
+        let origin_to_local =
+            |origin: &Vector3| Vector3::from(self.point_to_local(&Point3::from(*origin)));
+        let to_local =
+            |direction: &Vector3| self.inverse.transform_vector(direction) * (1.0 / scale);
+        (
+            Ray {
+                origin: origin_to_local(&ray.origin),
+                direction: direction * (1.0 / scale),
+                differential: ray.differential.map(|differential| RayDifferential {
+                    dx_origin: origin_to_local(&differential.dx_origin),
+                    dx_direction: to_local(&differential.dx_direction),
+                    dy_origin: origin_to_local(&differential.dy_origin),
+                    dy_direction: to_local(&differential.dy_direction),
+                }),
+            },
+            scale,
+        )
+    }
+
+    //factorul de scalare daca transformarea pastreaza unghiurile (rotatie, translatie
+    //si aceeasi scalare pe toate axele)
+    pub fn uniform_scale(&self) -> Option<f64> {
+        let axes = [
+            Vector3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vector3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+        ]
+        .map(|axis| self.matrix.transform_vector(&axis));
+        let scale = axes[0].norm();
+        let tolerance = 1e-9 * scale;
+        let uniform = axes
+            .iter()
+            .all(|axis| (axis.norm() - scale).abs() <= tolerance)
+            && axes[0].dot(&axes[1]).abs() <= tolerance * scale
+            && axes[1].dot(&axes[2]).abs() <= tolerance * scale
+            && axes[0].dot(&axes[2]).abs() <= tolerance * scale;
+        if uniform {
+            Some(scale)
+        } else {
+            None
+        }
+    }
+
+    //normalele se transforma cu inversa transpusa ca sa ramana perpendiculare pe suprafata
+    pub fn normal_to_world(&self, normal: &Normal3) -> Normal3 {
+        self.inverse.transform_normal(normal).normalize()
+    }
+
+    pub fn bounds_to_world(&self, bounds: &Aabb) -> Aabb {
+        let mut world = Aabb::empty();
+        for corner in 0..8 {
+            let point = Point3 {
+                x: if corner & 1 == 0 {
+                    bounds.min.x
+                } else {
+                    bounds.max.x
+                },
+                y: if corner & 2 == 0 {
+                    bounds.min.y
+                } else {
+                    bounds.max.y
+                },
+                z: if corner & 4 == 0 {
+                    bounds.min.z
+                } else {
+                    bounds.max.z
+                },
+            };
+            world = world.grow(&Vector3::from(self.point_to_world(&point)));
+        }
+        world
+    }
+}
+
+impl TryFrom<TransformDescription> for Transform {
+    type Error = String;
+
+    fn try_from(description: TransformDescription) -> Result<Self, Self::Error> {
+        Transform::from_description(description).map_err(|err| err.to_string())
+    }
+}
+
+impl From<Transform> for TransformDescription {
+    fn from(transform: Transform) -> Self {
+        transform.description
+    }
+}
+
+#[test]
+fn test_transformed_and_instanced_spheres() {
+    use crate::render::{Intersectable, SurfacePoint};
+    use crate::scenedata::{
+        test_material, test_scene, test_sphere, Color, Coloration, Element, Scene, SurfaceType,
+        Transformed,
+    };
+
+    let ball = |color: Color| {
+        test_sphere(
+            Vector3::zero(),
+            1.0,
+            test_material(color, 0.5, SurfaceType::Diffuse),
+        )
+    };
+    let translation = |x: f64, z: f64| Vector3 { x, y: 0.0, z };
+    let one = Vector3 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+    };
+    let ellipsoid = Element::Transformed(Transformed {
+        transform: Transform::new(
+            translation(0.0, -10.0),
+            Vector3::zero(),
+            Vector3 {
+                x: 3.0,
+                y: 1.0,
+                z: 2.0,
+            },
+        )
+        .unwrap(),
+        element: Box::new(ball(Color {
+            red: 0.0,
+            green: 1.0,
+            blue: 0.0,
+        })),
+    });
+    let mut scenemgr = test_scene(10, 10, Vec::new(), vec![ellipsoid]);
+    scenemgr.add_prototype(
+        "ball",
+        ball(Color {
+            red: 1.0,
+            green: 0.0,
+            blue: 0.0,
+        }),
+    );
+    scenemgr
+        .add_instance(
+            "ball",
+            Transform::new(translation(5.0, 0.0), Vector3::zero(), one).unwrap(),
+        )
+        .unwrap();
+    let quarter_turn = Vector3 {
+        x: 0.0,
+        y: 90.0,
+        z: 0.0,
+    };
+    scenemgr
+        .add_instance(
+            "ball",
+            Transform::new(translation(-5.0, 0.0), quarter_turn, one).unwrap(),
+        )
+        .unwrap();
+    let scene = scenemgr.scene;
+
+    //elipsoidul are semiaxa 2 pe z, deci e lovit la z = -8
+    let ray = Ray {
+        origin: Vector3::zero(),
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    };
+    let hit = scene.trace(&ray).unwrap().unwrap();
+    assert!((hit.distance - 8.0).abs() < 1e-9);
+    assert!((hit.object.surface_normal(&hit.surface_point(&ray)).z - 1.0).abs() < 1e-9);
+
+    //razele vecine trec prin aceeasi transformare ca raza, ca mipmap-urile sa aiba footprint
+    let offset = Vector3 {
+        x: 0.3,
+        y: 0.0,
+        z: 0.0,
+    };
+    let with_differential = Ray {
+        differential: Some(RayDifferential {
+            dx_origin: offset,
+            dx_direction: ray.direction,
+            dy_origin: ray.origin,
+            dy_direction: ray.direction,
+        }),
+        ..ray
+    };
+    let transform = match scene.objects()[0] {
+        crate::scenedata::Element::Transformed(ref t) => &t.transform,
+        _ => panic!("expected a transformed element"),
+    };
+    let (local, _) = transform.ray_to_local(&with_differential);
+    let differential = local
+        .differential
+        .expect("differential dropped in object space");
+    assert!(
+        (differential.dx_origin - Vector3::from(transform.point_to_local(&Point3::from(offset))))
+            .norm()
+            < 1e-9
+    );
+    assert!((differential.dx_direction - local.direction).norm() < 1e-9);
+
+    //normala pe elipsoid vine din inversa transpusa, nu din matricea directa
+    let side = Vector3 {
+        x: 3.0 * 0.5f64.sqrt(),
+        y: 0.0,
+        z: -10.0 + 2.0 * 0.5f64.sqrt(),
+    };
+    let normal = scene.objects()[0].surface_normal(&SurfacePoint::from(side));
+    let expected = Vector3 {
+        x: 1.0 / 3.0,
+        y: 0.0,
+        z: 1.0 / 2.0,
+    }
+    .normalize();
+    assert!((normal - expected).norm() < 1e-9);
+
+    let ray = Ray {
+        origin: Vector3 {
+            x: -5.0,
+            y: 0.0,
+            z: 5.0,
+        },
+        direction: Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        },
+        differential: None,
+    };
+    let hit = scene.trace(&ray).unwrap().unwrap();
+    assert!((hit.distance - 4.0).abs() < 1e-9);
+    match hit.object.material().coloration {
+        Coloration::Color(ref color) => assert_eq!(color.red, 1.0),
+        _ => panic!("expected the prototype material"),
+    }
+    //ambele instante folosesc acelasi prototip
+    assert_eq!(
+        std::sync::Arc::strong_count(&scene.prototypes[0].element),
+        3
+    );
+
+    //transformarile si instantele trec neschimbate prin json
+    let json = serde_json::to_string(&scene).unwrap();
+    let mut reloaded: Scene = serde_json::from_str(&json).unwrap();
+    reloaded.load_assets(std::path::Path::new("")).unwrap();
+    let hit = reloaded.trace(&ray).unwrap().unwrap();
+    assert!((hit.distance - 4.0).abs() < 1e-9);
+    assert!(matches!(reloaded.objects()[2], Element::Instance(_)));
+}
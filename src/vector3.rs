@@ -1,4 +1,4 @@
-use std::ops::{Add, Mul, Neg, Sub};
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use serde::{Deserialize, Serialize};
 
@@ -75,6 +75,67 @@ impl Sub for Vector3 {
     }
 }
 
+impl Div<f64> for Vector3 {
+    type Output = Vector3;
+
+    fn div(self, scalar: f64) -> Vector3 {
+        Vector3 {
+            x: self.x / scalar,
+            y: self.y / scalar,
+            z: self.z / scalar,
+        }
+    }
+}
+
+impl AddAssign for Vector3 {
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+        self.z += other.z;
+    }
+}
+
+impl SubAssign for Vector3 {
+    fn sub_assign(&mut self, other: Self) {
+        self.x -= other.x;
+        self.y -= other.y;
+        self.z -= other.z;
+    }
+}
+
+impl MulAssign<f64> for Vector3 {
+    fn mul_assign(&mut self, scalar: f64) {
+        self.x *= scalar;
+        self.y *= scalar;
+        self.z *= scalar;
+    }
+}
+
+//v[0] = x, v[1] = y, v[2] = z
+impl Index<usize> for Vector3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 index out of range: {}", axis),
+        }
+    }
+}
+
+impl IndexMut<usize> for Vector3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f64 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vector3 index out of range: {}", axis),
+        }
+    }
+}
+
 impl Vector3 {
     pub const fn zero() -> Self {
         Vector3 {
@@ -105,6 +166,22 @@ impl Vector3 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
+    //egalitate cu toleranta pe fiecare componenta
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        (self.x - other.x).abs() <= epsilon
+            && (self.y - other.y).abs() <= epsilon
+            && (self.z - other.z).abs() <= epsilon
+    }
+
+    //normala intoarsa spre partea din care vine directia (opusa ei)
+    pub fn face_forward(&self, direction: &Self) -> Self {
+        if self.dot(direction) > 0.0 {
+            -*self
+        } else {
+            *self
+        }
+    }
+
     pub fn cross(&self, other: &Self) -> Self {
         Vector3 {
             x: self.y * other.z - self.z * other.y,
@@ -112,4 +189,646 @@ impl Vector3 {
             z: self.x * other.y - self.y * other.x,
         }
     }
+
+    //rotatie in jurul axelor x, apoi y, apoi z, unghiurile in grade
+    pub fn rotate_euler(&self, degrees: &Vector3) -> Vector3 {
+        self.rotate_x(degrees.x)
+            .rotate_y(degrees.y)
+            .rotate_z(degrees.z)
+    }
+
+    pub fn rotate_euler_inverse(&self, degrees: &Vector3) -> Vector3 {
+        self.rotate_z(-degrees.z)
+            .rotate_y(-degrees.y)
+            .rotate_x(-degrees.x)
+    }
+
+    fn rotate_x(&self, degrees: f64) -> Vector3 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Vector3 {
+            x: self.x,
+            y: self.y * cos - self.z * sin,
+            z: self.y * sin + self.z * cos,
+        }
+    }
+
+    fn rotate_y(&self, degrees: f64) -> Vector3 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Vector3 {
+            x: self.x * cos + self.z * sin,
+            y: self.y,
+            z: -self.x * sin + self.z * cos,
+        }
+    }
+
+    fn rotate_z(&self, degrees: f64) -> Vector3 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Vector3 {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+            z: self.z,
+        }
+    }
+}
+
+//matrice 4x4 pentru transformari afine, pe randuri; vectorii sunt coloane (M * v)
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, other: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Matrix4 { m }
+    }
+}
+
+impl Matrix4 {
+    pub const fn identity() -> Matrix4 {
+        Matrix4 {
+            m: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    pub fn translation(offset: &Vector3) -> Matrix4 {
+        let mut matrix = Matrix4::identity();
+        matrix.m[0][3] = offset.x;
+        matrix.m[1][3] = offset.y;
+        matrix.m[2][3] = offset.z;
+        matrix
+    }
+
+    pub fn scaling(scale: &Vector3) -> Matrix4 {
+        let mut matrix = Matrix4::identity();
+        matrix.m[0][0] = scale.x;
+        matrix.m[1][1] = scale.y;
+        matrix.m[2][2] = scale.z;
+        matrix
+    }
+
+    pub fn rotation_x(degrees: f64) -> Matrix4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut matrix = Matrix4::identity();
+        matrix.m[1][1] = cos;
+        matrix.m[1][2] = -sin;
+        matrix.m[2][1] = sin;
+        matrix.m[2][2] = cos;
+        matrix
+    }
+
+    pub fn rotation_y(degrees: f64) -> Matrix4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut matrix = Matrix4::identity();
+        matrix.m[0][0] = cos;
+        matrix.m[0][2] = sin;
+        matrix.m[2][0] = -sin;
+        matrix.m[2][2] = cos;
+        matrix
+    }
+
+    pub fn rotation_z(degrees: f64) -> Matrix4 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut matrix = Matrix4::identity();
+        matrix.m[0][0] = cos;
+        matrix.m[0][1] = -sin;
+        matrix.m[1][0] = sin;
+        matrix.m[1][1] = cos;
+        matrix
+    }
+
+    //aceeasi conventie ca Vector3::rotate_euler: x, apoi y, apoi z
+    pub fn rotation_euler(degrees: &Vector3) -> Matrix4 {
+        Matrix4::rotation_z(degrees.z)
+            * Matrix4::rotation_y(degrees.y)
+            * Matrix4::rotation_x(degrees.x)
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4 { m }
+    }
+
+    //gauss-jordan cu pivotare partiala, None daca matricea e singulara
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.m;
+        let mut inverse = Matrix4::identity().m;
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inverse[column][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == column {
+                    continue;
+                }
+                let factor = a[row][column];
+                if factor == 0.0 {
+                    continue;
+                }
+                for j in 0..4 {
+                    a[row][j] -= factor * a[column][j];
+                    inverse[row][j] -= factor * inverse[column][j];
+                }
+            }
+        }
+        Some(Matrix4 { m: inverse })
+    }
+
+    //punctele sunt afectate si de translatie
+    pub fn transform_point(&self, point: &Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * point.x + m[0][1] * point.y + m[0][2] * point.z + m[0][3];
+        let y = m[1][0] * point.x + m[1][1] * point.y + m[1][2] * point.z + m[1][3];
+        let z = m[2][0] * point.x + m[2][1] * point.y + m[2][2] * point.z + m[2][3];
+        let w = m[3][0] * point.x + m[3][1] * point.y + m[3][2] * point.z + m[3][3];
+        if w == 1.0 || w == 0.0 {
+            Point3 { x, y, z }
+        } else {
+            Point3 {
+                x: x / w,
+                y: y / w,
+                z: z / w,
+            }
+        }
+    }
+
+    //self trebuie sa fie inversa matricei care muta punctele: normalele se inmultesc
+    //cu inversa transpusa ca sa ramana perpendiculare pe suprafata
+    pub fn transform_normal(&self, normal: &Normal3) -> Normal3 {
+        let m = &self.m;
+        Normal3 {
+            x: m[0][0] * normal.x + m[1][0] * normal.y + m[2][0] * normal.z,
+            y: m[0][1] * normal.x + m[1][1] * normal.y + m[2][1] * normal.z,
+            z: m[0][2] * normal.x + m[1][2] * normal.y + m[2][2] * normal.z,
+        }
+    }
+
+    pub fn approx_eq(&self, other: &Matrix4, epsilon: f64) -> bool {
+        self.m
+            .iter()
+            .flatten()
+            .zip(other.m.iter().flatten())
+            .all(|(a, b)| (a - b).abs() <= epsilon)
+    }
+
+    //directiile nu sunt afectate de translatie
+    pub fn transform_vector(&self, vector: &Vector3) -> Vector3 {
+        let m = &self.m;
+        Vector3 {
+            x: m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+            y: m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+            z: m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z,
+        }
+    }
+}
+
+impl Index<(usize, usize)> for Matrix4 {
+    type Output = f64;
+
+    fn index(&self, (row, column): (usize, usize)) -> &f64 {
+        &self.m[row][column]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix4 {
+    fn index_mut(&mut self, (row, column): (usize, usize)) -> &mut f64 {
+        &mut self.m[row][column]
+    }
+}
+
+//pozitie in spatiu: e afectata de translatie
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+//normala unei suprafete: se transforma cu inversa transpusa, nu cu matricea punctelor
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Normal3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl From<Vector3> for Point3 {
+    fn from(v: Vector3) -> Point3 {
+        Point3 {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<Point3> for Vector3 {
+    fn from(p: Point3) -> Vector3 {
+        Vector3 {
+            x: p.x,
+            y: p.y,
+            z: p.z,
+        }
+    }
+}
+
+impl From<Vector3> for Normal3 {
+    fn from(v: Vector3) -> Normal3 {
+        Normal3 {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+impl From<Normal3> for Vector3 {
+    fn from(n: Normal3) -> Vector3 {
+        Vector3 {
+            x: n.x,
+            y: n.y,
+            z: n.z,
+        }
+    }
+}
+
+//punct - punct = directie
+impl Sub for Point3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Point3) -> Vector3 {
+        Vector3::from(self) - Vector3::from(other)
+    }
+}
+
+impl Add<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn add(self, offset: Vector3) -> Point3 {
+        Point3::from(Vector3::from(self) + offset)
+    }
+}
+
+impl Sub<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn sub(self, offset: Vector3) -> Point3 {
+        Point3::from(Vector3::from(self) - offset)
+    }
+}
+
+impl AddAssign<Vector3> for Point3 {
+    fn add_assign(&mut self, offset: Vector3) {
+        *self = *self + offset;
+    }
+}
+
+impl Index<usize> for Point3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Point3 index out of range: {}", axis),
+        }
+    }
+}
+
+impl Neg for Normal3 {
+    type Output = Normal3;
+
+    fn neg(self) -> Normal3 {
+        Normal3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl Point3 {
+    pub const fn origin() -> Point3 {
+        Point3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    pub fn distance(&self, other: &Point3) -> f64 {
+        (*self - *other).norm()
+    }
+
+    pub fn approx_eq(&self, other: &Point3, epsilon: f64) -> bool {
+        Vector3::from(*self).approx_eq(&Vector3::from(*other), epsilon)
+    }
+}
+
+impl Normal3 {
+    pub fn normalize(&self) -> Normal3 {
+        Normal3::from(Vector3::from(*self).normalize())
+    }
+
+    pub fn dot(&self, direction: &Vector3) -> f64 {
+        Vector3::from(*self).dot(direction)
+    }
+
+    //intoarce normala spre partea din care vine directia
+    pub fn face_forward(&self, direction: &Vector3) -> Normal3 {
+        if self.dot(direction) > 0.0 {
+            -*self
+        } else {
+            *self
+        }
+    }
+
+    pub fn approx_eq(&self, other: &Normal3, epsilon: f64) -> bool {
+        Vector3::from(*self).approx_eq(&Vector3::from(*other), epsilon)
+    }
+}
+
+//rotatie unitara w + xi + yj + zk
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+//compunere: (a * b).rotate(v) == a.rotate(b.rotate(v))
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+impl Quaternion {
+    pub const fn identity() -> Quaternion {
+        Quaternion {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    pub fn from_axis_angle(axis: &Vector3, degrees: f64) -> Quaternion {
+        let axis = axis.normalize();
+        let (sin, cos) = (degrees.to_radians() / 2.0).sin_cos();
+        Quaternion {
+            w: cos,
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+        }
+    }
+
+    //aceeasi conventie ca Vector3::rotate_euler: x, apoi y, apoi z
+    pub fn from_euler(degrees: &Vector3) -> Quaternion {
+        let x_axis = Vector3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let y_axis = Vector3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let z_axis = Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        Quaternion::from_axis_angle(&z_axis, degrees.z)
+            * Quaternion::from_axis_angle(&y_axis, degrees.y)
+            * Quaternion::from_axis_angle(&x_axis, degrees.x)
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        let size = self.norm();
+        if size > 0.0 {
+            self.scale(1.0 / size)
+        } else {
+            Quaternion::identity()
+        }
+    }
+
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    fn scale(&self, factor: f64) -> Quaternion {
+        Quaternion {
+            w: self.w * factor,
+            x: self.x * factor,
+            y: self.y * factor,
+            z: self.z * factor,
+        }
+    }
+
+    pub fn rotate(&self, v: &Vector3) -> Vector3 {
+        let u = Vector3 {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        };
+        let t = u.cross(v) * 2.0;
+        *v + t * self.w + u.cross(&t)
+    }
+
+    //interpolare sferica pe drumul cel mai scurt, t intre 0 si 1
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        let mut other = *other;
+        let mut cos = self.dot(&other);
+        //q si -q sunt aceeasi rotatie
+        if cos < 0.0 {
+            other = other.scale(-1.0);
+            cos = -cos;
+        }
+        //aproape paraleli: interpolarea liniara e suficienta si evita impartirea la sin ~ 0
+        if cos > 0.9995 {
+            let lerp = Quaternion {
+                w: self.w + (other.w - self.w) * t,
+                x: self.x + (other.x - self.x) * t,
+                y: self.y + (other.y - self.y) * t,
+                z: self.z + (other.z - self.z) * t,
+            };
+            return lerp.normalize();
+        }
+        let angle = cos.acos();
+        let sin = angle.sin();
+        let a = ((1.0 - t) * angle).sin() / sin;
+        let b = (t * angle).sin() / sin;
+        Quaternion {
+            w: self.w * a + other.w * b,
+            x: self.x * a + other.x * b,
+            y: self.y * a + other.y * b,
+            z: self.z * a + other.z * b,
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let q = self.normalize();
+        let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+        Matrix4 {
+            m: [
+                [
+                    1.0 - 2.0 * (y * y + z * z),
+                    2.0 * (x * y - w * z),
+                    2.0 * (x * z + w * y),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * y + w * z),
+                    1.0 - 2.0 * (x * x + z * z),
+                    2.0 * (y * z - w * x),
+                    0.0,
+                ],
+                [
+                    2.0 * (x * z - w * y),
+                    2.0 * (y * z + w * x),
+                    1.0 - 2.0 * (x * x + y * y),
+                    0.0,
+                ],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    //q si -q reprezinta aceeasi rotatie
+    pub fn approx_eq(&self, other: &Quaternion, epsilon: f64) -> bool {
+        let same = (self.w - other.w).abs() <= epsilon
+            && (self.x - other.x).abs() <= epsilon
+            && (self.y - other.y).abs() <= epsilon
+            && (self.z - other.z).abs() <= epsilon;
+        same || (self.w + other.w).abs() <= epsilon
+            && (self.x + other.x).abs() <= epsilon
+            && (self.y + other.y).abs() <= epsilon
+            && (self.z + other.z).abs() <= epsilon
+    }
+}
+
+#[test]
+fn test_matrix_quaternion_and_normal_transforms() {
+    let degrees = Vector3 {
+        x: 30.0,
+        y: -45.0,
+        z: 120.0,
+    };
+    let v = Vector3 {
+        x: 1.0,
+        y: 2.0,
+        z: -3.0,
+    };
+    let rotation = Quaternion::from_euler(&degrees);
+    assert!(rotation
+        .rotate(&v)
+        .approx_eq(&v.rotate_euler(&degrees), 1e-12));
+    assert!(rotation
+        .to_matrix()
+        .approx_eq(&Matrix4::rotation_euler(&degrees), 1e-12));
+
+    let y_axis = Vector3 {
+        x: 0.0,
+        y: 1.0,
+        z: 0.0,
+    };
+    let start = Quaternion::identity();
+    let end = Quaternion::from_axis_angle(&y_axis, 90.0);
+    let half = start.slerp(&end, 0.5);
+    assert!(half.approx_eq(&Quaternion::from_axis_angle(&y_axis, 45.0), 1e-12));
+    assert!(start.slerp(&end, 1.0).approx_eq(&end, 1e-12));
+
+    let matrix = Matrix4::translation(&v) * rotation.to_matrix() * Matrix4::scaling(&v);
+    let inverse = matrix.inverse().unwrap();
+    assert!((matrix * inverse).approx_eq(&Matrix4::identity(), 1e-12));
+    assert_eq!(matrix.transpose().transpose(), matrix);
+
+    //normala unui plan ramane perpendiculara pe plan dupa o scalare neuniforma
+    let stretch = Matrix4::scaling(&Vector3 {
+        x: 4.0,
+        y: 1.0,
+        z: 1.0,
+    });
+    let tangent = stretch.transform_vector(&Vector3 {
+        x: 1.0,
+        y: -1.0,
+        z: 0.0,
+    });
+    let normal = stretch
+        .inverse()
+        .unwrap()
+        .transform_normal(&Normal3 {
+            x: 1.0,
+            y: 1.0,
+            z: 0.0,
+        })
+        .normalize();
+    assert!(normal.dot(&tangent).abs() < 1e-12);
+
+    //punctele se muta cu translatia, diferenta a doua puncte e o directie
+    let p = Point3::from(v);
+    let moved = matrix.transform_point(&p);
+    assert!(inverse.transform_point(&moved).approx_eq(&p, 1e-12));
+    assert_eq!((p + y_axis) - p, y_axis);
+    //o normala intoarsa spre partea din care vine raza
+    assert_eq!(y_axis.face_forward(&y_axis), -y_axis);
+    assert_eq!(y_axis.face_forward(&-y_axis), y_axis);
+    assert_eq!(v[2], -3.0);
+    assert_eq!(matrix[(3, 3)], 1.0);
 }